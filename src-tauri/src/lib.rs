@@ -1,40 +1,193 @@
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     error::Error,
     fs,
     process::Command,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     time::{Duration, Instant},
 };
 use tauri::{
     image::Image,
-    menu::{Menu, MenuItem},
-    tray::TrayIconBuilder,
-    AppHandle, Manager,
+    menu::{CheckMenuItem, Menu, MenuItem, Submenu},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Emitter, Manager,
 };
 use tauri_plugin_opener::OpenerExt;
+use tokio_util::sync::CancellationToken;
 
 // 轮播切换的最小间隔，防止频率过高导致 UI 频繁更新。
 const ROTATE_MIN_SECONDS: u64 = 3;
-// 发生错误后的最大退避秒数，避免长时间失败造成频繁请求。
+// 焦点品类快速刷新的最小间隔，限制额外请求量。
+const FOCUS_REFRESH_MIN_SECONDS: u64 = 2;
+// 临时加速的最小刷新间隔与最长持续时间（秒）。
+const TURBO_MIN_SECONDS: u64 = 1;
+const TURBO_MAX_DURATION_SECONDS: u64 = 3600;
+// K 线开始后多少秒内视为刚开始，此时开盘价与收盘价几乎相同，平盘趋势沿用上一次的方向。
+const FRESH_CANDLE_SECONDS: u64 = 15;
+// 开启保持图标时，连续多少次没有数据后才切换为等待图标。
+const HOLD_ICON_MAX_MISSES: u32 = 3;
+// 发生错误后默认的最大退避秒数，避免长时间失败造成频繁请求；可在设置中调整。
 const ERROR_BACKOFF_MAX_SECONDS: u64 = 300;
+// 首次出错时退避秒数相对刷新间隔的默认倍数。
+const ERROR_BACKOFF_MULTIPLIER: u64 = 3;
+// 网络错误退避期间，连通性探测的间隔秒数。
+const CONNECTIVITY_PROBE_SECONDS: u64 = 15;
+// 推送连接的心跳间隔秒数（服务端长时间收不到心跳会断开连接）。
+const STREAM_HEARTBEAT_SECONDS: u64 = 10;
+// 推送连接超过该秒数没有收到任何消息（包括心跳应答）时视为已断开。
+const STREAM_IDLE_TIMEOUT_SECONDS: u64 = 35;
+// 推送连接正常时批量 K 线请求的最小间隔，仅用于更新开盘价与 tooltip。
+const STREAM_KLINE_REFRESH_SECONDS: u64 = 60;
+// 推送接口的命令号：心跳、订阅最新成交价、成交价推送。
+const STREAM_CMD_HEARTBEAT: u32 = 22000;
+const STREAM_CMD_SUBSCRIBE_TRADE: u32 = 22004;
+const STREAM_CMD_TRADE_PUSH: u32 = 22998;
+// 行情接口的主地址，备用地址（设置中的 endpoints）在其连接失败后依次尝试。
+const API_BASE_URL: &str = "https://quote.alltick.io";
+// 连通性探测的目标地址（行情接口主机）。
+const CONNECTIVITY_PROBE_ADDR: &str = "quote.alltick.io:443";
+// 单次 HTTP 请求与推送连接建立的超时时间。
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// 单次连通性探测的超时秒数。
+const CONNECTIVITY_PROBE_TIMEOUT_SECONDS: u64 = 3;
+// 提供 token 的环境变量名，设置与旧版文件中都没有 token 时使用。
+const TOKEN_ENV_VAR: &str = "ALLTICK_TOKEN";
+// 托盘图标 ID，用于重启轮询时重新获取托盘。
+const TRAY_ID: &str = "xau-tray";
+// 组合指数虚拟品类的代码，与普通品类一起参与轮播与固定展示。
+const PORTFOLIO_CODE: &str = "@portfolio";
 // 设置文件名，保存在系统应用数据目录下。
 const SETTINGS_FILE: &str = "settings.json";
+// 退出时保存轮询状态的文件名，与设置文件同目录。
+const POLL_STATE_FILE: &str = "last_state.json";
+// 价格快照 CSV 文件名，与设置文件同目录。
+const SNAPSHOT_CSV_FILE: &str = "snapshots.csv";
+// 设置窗口位置与大小的保存文件名。
+const WINDOW_STATE_FILE: &str = "window_state.json";
+// 应用数据目录可写性自检的间隔（秒），磁盘写满或权限变化时提示设置无法保存。
+const DATA_DIR_CHECK_SECONDS: u64 = 60;
+// 可写性自检写入的临时文件名，写入后立即删除。
+const DATA_DIR_PROBE_FILE: &str = ".write_check";
+// 托盘图标创建失败后重试前的等待时间（毫秒）。
+const TRAY_RETRY_DELAY_MS: u64 = 500;
+// 日志文件名，超过 LOG_FILE_MAX_BYTES 时轮转为 .old。
+const LOG_FILE: &str = "xau-tray.log";
+const LOG_FILE_MAX_BYTES: u64 = 1024 * 1024;
+// 日志查看最多返回的行数，以及从文件末尾读取的最大字节数。
+const READ_LOGS_MAX_LINES: usize = 1000;
+const READ_LOGS_MAX_BYTES: u64 = 256 * 1024;
+// 退出时等待轮询任务收尾的最长时间（秒），超过后强制退出。
+const SHUTDOWN_GRACE_SECONDS: u64 = 15;
+// 锚点价格的最短复查间隔（秒），周期切换后 API 尚未出新 K 线时避免频繁重试。
+const ANCHOR_RECHECK_SECONDS: u64 = 600;
+// 同一业务错误连续出现多少次后提醒用户（如 token 过期）。
+const BUSINESS_ERROR_ALERT_THRESHOLD: u32 = 3;
+// 同一代码连续多少次请求无数据后，在 tooltip 中提示可能选错了接口类型。
+const EMPTY_DATA_HINT_THRESHOLD: u32 = 3;
+// token 出现业务错误后的冷却时长（秒），冷却结束后重新尝试。
+const TOKEN_COOLDOWN_SECONDS: u64 = 300;
+// 响应解析失败时，错误信息中保留的响应体最大字符数。
+const DECODE_BODY_PREVIEW_CHARS: usize = 200;
+// Alltick 支持的 K 线类型与名称，前端下拉框与校验共用这一张表。
+const KLINE_TYPES: [(u8, &str); 10] = [
+    (1, "1分钟"),
+    (2, "5分钟"),
+    (3, "15分钟"),
+    (4, "30分钟"),
+    (5, "1小时"),
+    (6, "2小时"),
+    (7, "4小时"),
+    (8, "日线"),
+    (9, "周线"),
+    (10, "月线"),
+];
+// Alltick K 线类型：1 分钟线。
+const KLINE_MINUTE: u8 = 1;
+// Alltick K 线类型：5 分钟线，用于 VWAP。
+const KLINE_FIVE_MINUTES: u8 = 2;
+// VWAP 每次拉取的 K 线数量，5 分钟线 288 根覆盖一整天。
+const VWAP_CANDLE_COUNT: u32 = 288;
+// VWAP 的重新拉取间隔（秒），不随每次刷新请求。
+const VWAP_REFRESH_SECONDS: u64 = 300;
+// Alltick 因单次请求的代码数量超过上限而拒绝时返回的 ret。
+const API_RET_TOO_MANY_CODES: i64 = 604;
+// 批次被缩小后，每隔多少秒尝试一次更大的批次。
+const BATCH_PROBE_SECONDS: u64 = 600;
 
 // 前端可配置的品类：code 是接口代码，label 是展示名称。
-#[derive(Serialize, Deserialize, Clone, Default)]
+// blend 不为空时，该品类的价格为多个成分代码价格的均值。
+// currency_symbol 为价格前的货币符号（如 "$"、"¥"），默认不显示。
+// invert_trend 用于空头持仓：上涨显示为下跌箭头与图标，价格文本不变。
+// entry_price 为持仓入场价，设置后 tooltip 中追加相对入场价的涨跌幅。
+// enabled 为 false 时保留在列表中，但不请求也不展示。
+// invert_price 显示价格的倒数（如 USDCNH 显示为 CNHUSD），涨跌方向随之反转，缓存仍保存原始价格。
+// always_open 标记是否全天候交易（如加密货币），未设置时按接口类型推断；非全天候品类不追加非最新提示符。
+#[derive(Serialize, Deserialize, Clone)]
 struct SymbolItem {
     code: String,
     label: String,
+    #[serde(default)]
+    blend: Option<Vec<String>>,
+    #[serde(default)]
+    currency_symbol: Option<String>,
+    #[serde(default)]
+    invert_trend: bool,
+    #[serde(default)]
+    entry_price: Option<f64>,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    #[serde(default)]
+    session: Option<MarketSession>,
+    #[serde(default)]
+    invert_price: bool,
+    #[serde(default)]
+    always_open: Option<bool>,
+}
+
+impl Default for SymbolItem {
+    fn default() -> Self {
+        Self {
+            code: String::new(),
+            label: String::new(),
+            blend: None,
+            currency_symbol: None,
+            invert_trend: false,
+            entry_price: None,
+            enabled: true,
+            session: None,
+            invert_price: false,
+            always_open: None,
+        }
+    }
+}
+
+// 品类的交易时段：open/close 为交易所当地时间 "HH:MM"，timezone 为 IANA 时区名（如 "America/New_York"）。
+#[derive(Serialize, Deserialize, Clone)]
+struct MarketSession {
+    open: String,
+    close: String,
+    timezone: String,
+}
+
+// 自选列表：一组品类，可在托盘菜单中快速切换；当前列表的内容与 symbols 保持一致。
+#[derive(Serialize, Deserialize, Clone)]
+struct Watchlist {
+    name: String,
+    #[serde(default)]
+    symbols: Vec<SymbolItem>,
 }
 
-// 价格显示方式：轮播或固定单个品类。
+// 价格显示方式：轮播、固定单个品类或展示当前涨跌幅最大的品类。
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 #[serde(rename_all = "snake_case")]
 enum DisplayMode {
     Rotate,
     Fixed,
+    TopMover,
 }
 
 // 后端 API 类型：商品或股票。
@@ -45,6 +198,100 @@ enum ApiType {
     Stock,
 }
 
+impl ApiType {
+    // tooltip 提示中使用的接口名称。
+    fn label(self, locale: &str) -> &'static str {
+        match self {
+            Self::Commodity => tr(locale, "api_commodity"),
+            Self::Stock => tr(locale, "api_stock"),
+        }
+    }
+
+    // 根据代码形态粗略推断所属接口：带市场后缀（如 .US/.HK/.SH/.SZ）的视为股票，其余视为商品。
+    fn guess_for_code(code: &str) -> Self {
+        const STOCK_MARKETS: [&str; 4] = ["US", "HK", "SH", "SZ"];
+        match code.rsplit_once('.') {
+            Some((_, market)) if STOCK_MARKETS.contains(&market.to_ascii_uppercase().as_str()) => {
+                Self::Stock
+            }
+            _ => Self::Commodity,
+        }
+    }
+}
+
+// 涨跌幅参照锚点：日开盘、周开盘或月开盘；设置中也可写作 day_open/week_open/month_open。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum ChangeAnchor {
+    #[serde(alias = "day_open")]
+    Day,
+    #[serde(alias = "week_open")]
+    Week,
+    #[serde(alias = "month_open")]
+    Month,
+}
+
+impl ChangeAnchor {
+    // 对应的 Alltick K 线类型：8 日线、9 周线、10 月线。
+    fn kline_type(self) -> u8 {
+        match self {
+            Self::Day => 8,
+            Self::Week => 9,
+            Self::Month => 10,
+        }
+    }
+
+    // tooltip 中展示的锚点名称。
+    fn label(self, locale: &str) -> &'static str {
+        match self {
+            Self::Day => tr(locale, "anchor_day"),
+            Self::Week => tr(locale, "anchor_week"),
+            Self::Month => tr(locale, "anchor_month"),
+        }
+    }
+
+    // 计算时间所属周期的标识，用于判断是否跨日/跨周/跨月。
+    fn period_key<Tz: chrono::TimeZone>(self, time: &chrono::DateTime<Tz>) -> (i32, u32) {
+        use chrono::Datelike;
+        match self {
+            Self::Day => (time.year(), time.ordinal()),
+            Self::Week => {
+                let week = time.iso_week();
+                (week.year(), week.week())
+            }
+            Self::Month => (time.year(), time.month()),
+        }
+    }
+}
+
+// 驱动托盘标题的价格来源：K 线收盘价或最新成交价。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum PriceSource {
+    CandleClose,
+    LastTick,
+}
+
+impl Default for PriceSource {
+    fn default() -> Self {
+        Self::CandleClose
+    }
+}
+
+// 行情传输方式：定时轮询，或 WebSocket 推送（连接失败时回退为轮询）。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Transport {
+    Poll,
+    Stream,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::Poll
+    }
+}
+
 impl Default for ApiType {
     fn default() -> Self {
         Self::Commodity
@@ -57,6 +304,74 @@ impl Default for DisplayMode {
     }
 }
 
+// 托盘图标左键点击的行为：弹出菜单、打开设置、切换到下一个品类或复制当前价格。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum ClickAction {
+    Menu,
+    Settings,
+    NextSymbol,
+    CopyPrice,
+}
+
+impl Default for ClickAction {
+    fn default() -> Self {
+        Self::Menu
+    }
+}
+
+// 行情数据源：目前仅支持 Alltick，新增数据源时在此扩展并实现 QuoteProvider；
+// 新数据源只负责批量报价，附加数据仍取自 Alltick（见 alltick_extras_enabled）。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Provider {
+    Alltick,
+}
+
+impl Default for Provider {
+    fn default() -> Self {
+        Self::Alltick
+    }
+}
+
+// tooltip 中时间的显示格式：24 小时制或 12 小时制。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum TimeFormat {
+    H24,
+    H12,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self::H24
+    }
+}
+
+// 价格保留两位小数时的舍入方式：沿用格式化器的默认行为，或四舍五入（0.5 向远离零的方向进位）。
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum RoundingMode {
+    HalfEven,
+    HalfUp,
+}
+
+impl Default for RoundingMode {
+    fn default() -> Self {
+        Self::HalfEven
+    }
+}
+
+impl TimeFormat {
+    // 对应的 chrono 格式串。
+    fn pattern(self) -> &'static str {
+        match self {
+            Self::H24 => "%H:%M:%S",
+            Self::H12 => "%I:%M:%S %p",
+        }
+    }
+}
+
 // 默认的行情刷新间隔（秒）。
 fn default_refresh_seconds() -> u64 {
     10
@@ -67,6 +382,61 @@ fn default_rotate_seconds() -> u64 {
     10
 }
 
+// 默认语言：跟随系统。
+fn default_locale() -> String {
+    "auto".to_string()
+}
+
+// 默认的 tooltip 最大行数。
+fn default_max_tooltip_lines() -> usize {
+    20
+}
+
+// 布尔设置项默认开启。
+fn default_true() -> bool {
+    true
+}
+
+// 默认的错误提示符号。
+fn default_error_indicator() -> String {
+    "🔴".to_string()
+}
+
+// 默认的数据非最新提示符号。
+fn default_stale_indicator() -> String {
+    "*".to_string()
+}
+
+// 默认的品类名称模板，仅展示名称。
+fn default_label_template() -> String {
+    "{label}".to_string()
+}
+
+// 固定宽度标题默认假设的整数位数。
+fn default_title_integer_digits() -> usize {
+    5
+}
+
+// 默认的最大退避秒数。
+fn default_max_backoff_seconds() -> u64 {
+    ERROR_BACKOFF_MAX_SECONDS
+}
+
+// 默认的首次退避倍数。
+fn default_backoff_multiplier() -> u64 {
+    ERROR_BACKOFF_MULTIPLIER
+}
+
+// 默认的标题分隔符：名称与价格之间一个空格。
+fn default_title_separator() -> String {
+    " ".to_string()
+}
+
+// 默认的无数据占位符。
+fn default_no_data_placeholder() -> String {
+    "--".to_string()
+}
+
 // 持久化配置：token、品类列表、刷新/轮播策略、固定展示等。
 #[derive(Serialize, Deserialize, Clone)]
 struct QuoteSettings {
@@ -86,6 +456,104 @@ struct QuoteSettings {
     fixed_symbol: Option<String>,
     #[serde(default)]
     use_system_proxy: bool,
+    #[serde(default)]
+    anchor: Option<ChangeAnchor>,
+    #[serde(default)]
+    show_volume: bool,
+    #[serde(default = "default_label_template")]
+    label_template: String,
+    #[serde(default)]
+    quiet_hours: Option<(u8, u8)>,
+    #[serde(default)]
+    price_source: PriceSource,
+    #[serde(default = "default_locale")]
+    locale: String,
+    #[serde(default)]
+    flat_threshold_percent: f64,
+    #[serde(default = "default_error_indicator")]
+    error_indicator: String,
+    #[serde(default = "default_stale_indicator")]
+    stale_indicator: String,
+    #[serde(default)]
+    show_health: bool,
+    #[serde(default)]
+    metrics_port: Option<u16>,
+    #[serde(default = "default_true")]
+    stale_marker_in_title: bool,
+    #[serde(default = "default_max_tooltip_lines")]
+    max_tooltip_lines: usize,
+    #[serde(default)]
+    show_vwap: bool,
+    #[serde(default)]
+    show_prev_close: bool,
+    #[serde(default)]
+    momentum_window: usize,
+    #[serde(default)]
+    show_abs_change: bool,
+    #[serde(default)]
+    focus_refresh_seconds: Option<u64>,
+    #[serde(default)]
+    strip_market_suffix: bool,
+    #[serde(default)]
+    watchlists: Vec<Watchlist>,
+    #[serde(default)]
+    active_watchlist: usize,
+    #[serde(default = "default_title_separator")]
+    title_separator: String,
+    #[serde(default)]
+    trend_from_entry: bool,
+    #[serde(default)]
+    error_icon_only: bool,
+    #[serde(default)]
+    smoothing_alpha: Option<f64>,
+    #[serde(default)]
+    transport: Transport,
+    #[serde(default)]
+    endpoints: Vec<String>,
+    #[serde(default)]
+    fixed_width_title: bool,
+    #[serde(default = "default_title_integer_digits")]
+    title_integer_digits: usize,
+    #[serde(default)]
+    show_api_ret: bool,
+    #[serde(default)]
+    mock_mode: bool,
+    #[serde(default)]
+    lightweight_fetch: bool,
+    #[serde(default)]
+    left_click_action: ClickAction,
+    #[serde(default)]
+    time_format: TimeFormat,
+    #[serde(default)]
+    portfolio: Vec<(String, f64)>,
+    #[serde(default = "default_max_backoff_seconds")]
+    max_backoff_seconds: u64,
+    #[serde(default = "default_backoff_multiplier")]
+    backoff_multiplier: u64,
+    #[serde(default)]
+    baseline_offset: u32,
+    #[serde(default)]
+    rotate_request_order: bool,
+    #[serde(default)]
+    max_jump_percent: Option<f64>,
+    #[serde(default)]
+    rounding: RoundingMode,
+    #[serde(default)]
+    show_ma: Option<u32>,
+    #[serde(default)]
+    max_title_chars: Option<usize>,
+    #[serde(default)]
+    hold_last_icon: bool,
+    #[serde(default)]
+    align_to_clock: bool,
+    #[serde(default)]
+    min_title_change: f64,
+    #[serde(default = "default_no_data_placeholder")]
+    no_data_placeholder: String,
+    #[serde(default)]
+    allow_empty_symbols: bool,
+    #[serde(default)]
+    provider: Provider,
 }
 
 impl Default for QuoteSettings {
@@ -99,31 +567,197 @@ impl Default for QuoteSettings {
             rotate_seconds: default_rotate_seconds(),
             fixed_symbol: None,
             use_system_proxy: false,
+            anchor: None,
+            show_volume: false,
+            label_template: default_label_template(),
+            quiet_hours: None,
+            price_source: PriceSource::CandleClose,
+            locale: default_locale(),
+            flat_threshold_percent: 0.0,
+            error_indicator: default_error_indicator(),
+            stale_indicator: default_stale_indicator(),
+            show_health: false,
+            metrics_port: None,
+            stale_marker_in_title: true,
+            max_tooltip_lines: default_max_tooltip_lines(),
+            show_vwap: false,
+            show_prev_close: false,
+            momentum_window: 0,
+            show_abs_change: false,
+            focus_refresh_seconds: None,
+            strip_market_suffix: false,
+            watchlists: Vec::new(),
+            active_watchlist: 0,
+            title_separator: default_title_separator(),
+            trend_from_entry: false,
+            error_icon_only: false,
+            smoothing_alpha: None,
+            transport: Transport::Poll,
+            endpoints: Vec::new(),
+            fixed_width_title: false,
+            title_integer_digits: default_title_integer_digits(),
+            show_api_ret: false,
+            mock_mode: false,
+            lightweight_fetch: false,
+            left_click_action: ClickAction::Menu,
+            time_format: TimeFormat::H24,
+            portfolio: Vec::new(),
+            max_backoff_seconds: default_max_backoff_seconds(),
+            backoff_multiplier: default_backoff_multiplier(),
+            baseline_offset: 0,
+            rotate_request_order: false,
+            max_jump_percent: None,
+            rounding: RoundingMode::HalfEven,
+            show_ma: None,
+            max_title_chars: None,
+            hold_last_icon: false,
+            align_to_clock: false,
+            min_title_change: 0.0,
+            no_data_placeholder: default_no_data_placeholder(),
+            allow_empty_symbols: false,
+            provider: Provider::default(),
         }
     }
 }
 
-// 全局状态：保存当前配置，供命令与轮询任务共享。
+// 全局状态：保存当前配置、运行指标与退出信号，供命令、轮询任务与指标端点共享。
 #[derive(Default)]
 struct AppState {
     settings: Arc<Mutex<QuoteSettings>>,
+    metrics: Arc<Mutex<PollMetrics>>,
+    shutdown: Arc<ShutdownSignal>,
+    polling: Mutex<CancellationToken>,
+    display_lock: Mutex<Option<String>>,
+    // 窗口重新获得焦点时置位，提示轮询任务网络可能已恢复。
+    network_hint: AtomicBool,
+    // 临时加速：(结束时间, 刷新间隔秒数)，仅保存在内存中。
+    turbo: Mutex<Option<(Instant, u64)>>,
+    // 左键点击请求切换到下一个品类时置位，由轮询任务处理。
+    next_symbol: AtomicBool,
+    // 手动请求立即刷新时置位，由轮询任务处理并清零错误退避。
+    refresh_requested: AtomicBool,
+    // 托盘图标创建失败的原因，设置窗口据此提示用户。
+    tray_error: Mutex<Option<String>>,
+}
+
+// 获取互斥锁；持有者 panic 导致锁中毒时仍取出数据继续使用，避免一次 panic 连带后续所有调用失败。
+fn lock_or_recover<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// 轮询运行指标：请求计数、退避与最近一次延迟、各品类最新价格与趋势、轮询状态。
+// 由轮询任务在每次刷新后发布，供指标端点与前端快照读取。
+#[derive(Default)]
+struct PollMetrics {
+    requests_total: u64,
+    failures_total: u64,
+    // 因跳变检测被暂缓采用的价格次数。
+    rejected_prices_total: u64,
+    backoff_seconds: u64,
+    last_latency_ms: u64,
+    last_prices: HashMap<String, f64>,
+    trends: HashMap<String, String>,
+    last_refresh_at: Option<String>,
+    last_error: Option<FetchError>,
+    consecutive_successes: u32,
+    consecutive_failures: u32,
+    displayed_code: Option<String>,
+    tooltip: String,
+    // 本次会话（启动或重置以来）各品类的 (首个价格, 最高, 最低)。
+    session_ranges: HashMap<String, (f64, f64, f64)>,
+    session_started_at: Option<String>,
+}
+
+// 单个品类的会话统计：启动（或重置）以来的首个价格、最高、最低、当前价格与涨跌幅。
+#[derive(Serialize)]
+struct SymbolSessionStats {
+    code: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    current: f64,
+    change_percent: f64,
+}
+
+// 会话统计：开始时间与各品类统计，品类顺序与设置一致。
+#[derive(Serialize)]
+struct SessionStats {
+    started_at: Option<String>,
+    symbols: Vec<SymbolSessionStats>,
+}
+
+// 轮询状态摘要，随应用快照返回给前端。
+#[derive(Serialize)]
+struct PollStatus {
+    last_refresh_at: Option<String>,
+    last_error: Option<FetchError>,
+    consecutive_successes: u32,
+    consecutive_failures: u32,
+    backoff_seconds: u64,
+}
+
+// 应用快照：设置、最新价格与趋势、轮询状态与代理信息，一次返回给前端。
+#[derive(Serialize)]
+struct AppSnapshot {
+    settings: QuoteSettings,
+    prices: HashMap<String, f64>,
+    trends: HashMap<String, String>,
+    status: PollStatus,
+    proxy: ProxyInfo,
 }
 
-// 单条 K 线数据（这里只取开盘价/收盘价与时间戳）。
+// 单条 K 线数据（这里只取开盘/收盘/最高/最低价、成交量与时间戳）。
 #[derive(Deserialize)]
 struct ApiKline {
     timestamp: String,
     open_price: String,
     close_price: String,
+    #[serde(default)]
+    high_price: Option<String>,
+    #[serde(default)]
+    low_price: Option<String>,
+    #[serde(default)]
+    volume: Option<String>,
+}
+
+// 用于 VWAP 计算的单根 K 线。
+#[derive(Clone, Copy, Debug)]
+struct Candle {
+    timestamp: u64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+}
+
+// 单个品类解析后的行情：最新价、K 线时间戳、开盘价与成交量（可能缺失）。
+#[derive(Clone, Copy, Debug)]
+struct Quote {
+    price: f64,
+    timestamp: u64,
+    open: f64,
+    volume: Option<f64>,
 }
 
-// 批量请求的响应结构。
+// 批量请求的响应结构；elapsed_ms 不来自接口，由请求方记录本次请求耗时。
 #[derive(Deserialize)]
 struct BatchResp {
     ret: i64,
     #[serde(default)]
     msg: Option<String>,
     data: BatchData,
+    #[serde(skip)]
+    elapsed_ms: u128,
+}
+
+// 批量请求的元信息：接口 ret、返回的品类条数与耗时，用于 tooltip 调试信息。
+#[derive(Clone, Copy)]
+struct FetchMeta {
+    ret: i64,
+    items: usize,
+    elapsed_ms: u128,
 }
 
 // 批量请求返回的 data 部分。
@@ -132,6 +766,30 @@ struct BatchData {
     kline_list: Vec<BatchItem>,
 }
 
+// 最新成交（tick）请求的响应结构。
+#[derive(Deserialize)]
+struct TickResp {
+    ret: i64,
+    #[serde(default)]
+    msg: Option<String>,
+    data: TickData,
+}
+
+// tick 请求返回的 data 部分。
+#[derive(Deserialize)]
+struct TickData {
+    tick_list: Vec<ApiTick>,
+}
+
+// 单条成交数据（只取代码、价格与成交时间）。
+#[derive(Deserialize)]
+struct ApiTick {
+    code: String,
+    price: String,
+    #[serde(default)]
+    tick_time: Option<String>,
+}
+
 // 每个品类的 K 线返回。
 #[derive(Deserialize)]
 struct BatchItem {
@@ -145,14 +803,17 @@ fn default_symbols() -> Vec<SymbolItem> {
         SymbolItem {
             code: "XAUUSD".into(),
             label: "黄金".into(),
+            ..Default::default()
         },
         SymbolItem {
             code: "Silver".into(),
             label: "白银".into(),
+            ..Default::default()
         },
         SymbolItem {
             code: "BTCUSDT".into(),
             label: "比特币".into(),
+            ..Default::default()
         },
     ]
 }
@@ -163,18 +824,29 @@ fn default_stock_symbols() -> Vec<SymbolItem> {
         SymbolItem {
             code: "000001.SH".into(),
             label: "上证指数".into(),
+            ..Default::default()
         },
         SymbolItem {
             code: "HSI.HK".into(),
             label: "恒生指数".into(),
+            ..Default::default()
         },
         SymbolItem {
             code: ".IXIC.US".into(),
             label: "纳斯达克指数".into(),
+            ..Default::default()
         },
     ]
 }
 
+// 按 API 类型选择默认品类列表。
+fn default_symbols_for(api_type: ApiType) -> Vec<SymbolItem> {
+    match api_type {
+        ApiType::Commodity => default_symbols(),
+        ApiType::Stock => default_stock_symbols(),
+    }
+}
+
 // 拼出设置文件路径（应用数据目录下）。
 fn settings_file_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
     let base = app.path().app_data_dir().map_err(|e| e.to_string())?;
@@ -187,74 +859,514 @@ fn legacy_token_file_path(app: &AppHandle) -> Result<std::path::PathBuf, String>
     Ok(base.join("token.txt"))
 }
 
-// Debug 模式输出日志，Release 模式保持安静。
-#[cfg(debug_assertions)]
+// 日志文件路径，启动时初始化；未初始化时只输出到终端。
+static LOG_FILE_PATH: OnceLock<std::path::PathBuf> = OnceLock::new();
+
+// 日志写入锁：串行化追加与轮转，避免多个线程同时轮转同一个文件。
+static LOG_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+// Debug 模式输出到终端；日志文件初始化后同时追加写入，供设置窗口查看。
+// 写入前检查文件大小，超过上限时轮转，长时间运行也不会无限增长。
 fn log_line(message: &str) {
+    use std::io::Write;
+
+    #[cfg(debug_assertions)]
     println!("{message}");
+    if let Some(path) = LOG_FILE_PATH.get() {
+        let _guard = lock_or_recover(&LOG_WRITE_LOCK);
+        rotate_log_file(path);
+        if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(path) {
+            let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+            let _ = writeln!(file, "{now} {message}");
+        }
+    }
+}
+
+// 日志文件超过上限时重命名为 .old（覆盖上一份旧日志）。
+fn rotate_log_file(path: &std::path::Path) {
+    if fs::metadata(path).is_ok_and(|meta| meta.len() > LOG_FILE_MAX_BYTES) {
+        let _ = fs::rename(path, path.with_file_name(format!("{LOG_FILE}.old")));
+    }
+}
+
+// 初始化日志文件：放在应用数据目录，超过上限时先轮转旧文件。
+fn init_log_file(app: &AppHandle) {
+    let Ok(base) = app.path().app_data_dir() else {
+        return;
+    };
+    if fs::create_dir_all(&base).is_err() {
+        return;
+    }
+    let path = base.join(LOG_FILE);
+    rotate_log_file(&path);
+    let _ = LOG_FILE_PATH.set(path);
+}
+
+// 读取系统语言：macOS 优先读取全局 AppleLocale，其次读环境变量，最后默认中文。
+fn system_locale() -> &'static str {
+    static SYSTEM_LOCALE: OnceLock<String> = OnceLock::new();
+    SYSTEM_LOCALE.get_or_init(|| {
+        #[cfg(target_os = "macos")]
+        if let Ok(output) = Command::new("defaults")
+            .args(["read", "-g", "AppleLocale"])
+            .output()
+        {
+            let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if output.status.success() && !value.is_empty() {
+                return value;
+            }
+        }
+        for key in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+            if let Ok(value) = std::env::var(key) {
+                let value = value.trim();
+                if !value.is_empty() && value != "C" && value != "POSIX" {
+                    return value.to_string();
+                }
+            }
+        }
+        "zh-CN".to_string()
+    })
+}
+
+// 解析实际生效的语言：auto 时使用系统语言。
+fn effective_locale(locale: &str) -> &str {
+    if locale == "auto" {
+        system_locale()
+    } else {
+        locale
+    }
 }
 
-#[cfg(not(debug_assertions))]
-fn log_line(_message: &str) {}
+// 托盘文案表：英文环境返回英文，其余返回中文。
+fn tr(locale: &str, key: &str) -> &'static str {
+    let english = locale.to_ascii_lowercase().starts_with("en");
+    match (key, english) {
+        ("app_title", false) => "盯价助手",
+        ("app_title", true) => "Price Watch",
+        ("initial_tooltip", false) => "请先进行必要的设置",
+        ("initial_tooltip", true) => "Please complete the settings first",
+        ("no_symbols", false) => "无品类",
+        ("no_symbols", true) => "No symbols",
+        ("no_symbols_tooltip", false) => "请在设置中添加品类",
+        ("no_symbols_tooltip", true) => "Please add symbols in settings",
+        ("set_token", false) => "设置 Token",
+        ("set_token", true) => "Set Token",
+        ("set_token_tooltip", false) => "请先在设置中填写 Alltick Token",
+        ("set_token_tooltip", true) => "Please enter your Alltick token in settings",
+        ("menu_settings", false) => "设置",
+        ("menu_settings", true) => "Settings",
+        ("menu_about", false) => "关于",
+        ("menu_about", true) => "About",
+        ("menu_quit", false) => "退出",
+        ("menu_quit", true) => "Quit",
+        ("error", false) => "错误",
+        ("error", true) => "Error",
+        ("volume", false) => "量",
+        ("volume", true) => "Vol",
+        ("market_closed", false) => "休市",
+        ("market_closed", true) => "Closed",
+        ("portfolio", false) => "组合",
+        ("portfolio", true) => "Portfolio",
+        ("tray_error", false) => "托盘图标创建失败：{err}，可在此窗口中修改设置",
+        ("tray_error", true) => {
+            "Failed to create the tray icon: {err}. You can still change settings in this window"
+        }
+        ("err_log_uninitialized", false) => "日志文件未初始化",
+        ("err_log_uninitialized", true) => "Log file is not initialized",
+        ("prev_close", false) => "昨收",
+        ("prev_close", true) => "Prev close",
+        ("entry", false) => "入场",
+        ("entry", true) => "entry",
+        ("anchor_day", false) => "较日开",
+        ("anchor_day", true) => "vs day open",
+        ("anchor_week", false) => "较周开",
+        ("anchor_week", true) => "vs week open",
+        ("anchor_month", false) => "较月开",
+        ("anchor_month", true) => "vs month open",
+        ("err_token_empty", false) => "token 为空",
+        ("err_token_empty", true) => "Token is empty",
+        ("err_no_symbols", false) => "没有品类",
+        ("err_no_symbols", true) => "No symbols configured",
+        ("err_no_kline_data", false) => "{code} 没有 K 线数据",
+        ("err_no_kline_data", true) => "No kline data for {code}",
+        ("blend_partial", false) => "部分",
+        ("blend_partial", true) => "partial",
+        ("health", false) => "连续成功 {successes} / 失败 {failures}",
+        ("health", true) => "Successes {successes} / failures {failures}",
+        ("api_commodity", false) => "商品接口",
+        ("api_commodity", true) => "the commodity API",
+        ("api_stock", false) => "股票接口",
+        ("api_stock", true) => "the stock API",
+        ("empty_data_hint", false) => "{code} 无数据 — 可能需切换为{api}",
+        ("empty_data_hint", true) => "{code} has no data — try switching to {api}",
+        ("tokens_exhausted", false) => "全部 Token 请求失败：{msg}",
+        ("tokens_exhausted", true) => "All tokens failed: {msg}",
+        ("omitted", false) => "… 其余 {count} 项",
+        ("omitted", true) => "… {count} more",
+        ("updated_at", false) => "更新于 {time}",
+        ("updated_at", true) => "Updated {time}",
+        ("rotation_warning", false) => {
+            "轮播一圈仅需 {cycle} 秒，短于刷新间隔 {refresh} 秒，会重复显示相同数据"
+        }
+        ("rotation_warning", true) => {
+            "A rotation cycle takes only {cycle}s, shorter than the {refresh}s refresh interval, so the same data repeats"
+        }
+        ("business_error_alert", false) => "行情接口连续返回错误：{msg}，请检查 Token 或设置",
+        ("business_error_alert", true) => {
+            "The quote API keeps returning an error: {msg}. Please check your token or settings"
+        }
+        ("menu_watchlists", false) => "自选列表",
+        ("menu_watchlists", true) => "Watchlists",
+        ("watchlist_default", false) => "默认",
+        ("watchlist_default", true) => "Default",
+        ("watchlist_numbered", false) => "列表 {index}",
+        ("watchlist_numbered", true) => "List {index}",
+        ("err_turbo_duration", false) => "加速时长不能超过 {max} 秒",
+        ("err_turbo_duration", true) => "Turbo duration must not exceed {max} seconds",
+        ("err_symbol_not_found", false) => "品类 {code} 不存在",
+        ("err_symbol_not_found", true) => "Symbol {code} not found",
+        ("err_unknown_field", false) => "未知的设置项: {field}",
+        ("err_unknown_field", true) => "Unknown setting: {field}",
+        ("stream_proxy_unsupported", false) => "推送连接不支持代理，检测到代理时改用定时轮询",
+        ("stream_proxy_unsupported", true) => {
+            "The stream transport does not support proxies; polling is used while a proxy is active"
+        }
+        ("menu_copy_all", false) => "复制全部",
+        ("menu_copy_all", true) => "Copy All",
+        ("data_dir_unwritable", false) => "⚠ 设置无法保存: {err}",
+        ("data_dir_unwritable", true) => "⚠ Settings cannot be saved: {err}",
+        ("menu_refresh_now", false) => "立即刷新",
+        ("menu_refresh_now", true) => "Refresh Now",
+        ("err_mock_unavailable", false) => "模拟数据模式下不可用",
+        ("err_mock_unavailable", true) => "Unavailable in mock mode",
+        _ => "",
+    }
+}
 
 // 读取并规范化设置，必要时迁移旧 token。
 fn load_settings(app: &AppHandle) -> QuoteSettings {
-    let mut settings = if let Ok(path) = settings_file_path(app) {
-        fs::read_to_string(path)
-            .ok()
-            .and_then(|content| serde_json::from_str::<QuoteSettings>(&content).ok())
-            .unwrap_or_default()
-    } else {
-        QuoteSettings::default()
-    };
+    let mut settings = settings_file_path(app)
+        .ok()
+        .and_then(|path| read_settings_file(&path))
+        .unwrap_or_default();
 
     if settings.token.trim().is_empty() {
         if let Ok(path) = legacy_token_file_path(app) {
             if let Ok(token) = fs::read_to_string(path) {
                 settings.token = token.trim().to_string();
+                log_line("[xau-tray] token source: legacy token file");
             }
         }
     }
 
+    // 仍为空时读取环境变量，该 token 不会写回设置文件。
+    if settings.token.trim().is_empty() {
+        if let Some(token) = env_token() {
+            settings.token = token;
+            log_line(&format!("[xau-tray] token source: {TOKEN_ENV_VAR} env"));
+        }
+    }
+
     normalize_settings(settings)
 }
 
+// 读取并解析设置文件；文件不存在或内容无法解析时返回 None。
+fn read_settings_file(path: &std::path::Path) -> Option<QuoteSettings> {
+    fs::read(path)
+        .ok()
+        .map(|bytes| decode_settings_text(&bytes))
+        .and_then(|content| serde_json::from_str::<QuoteSettings>(&content).ok())
+}
+
+// 将设置文件内容解码为文本：去掉 UTF-8 BOM（如 Windows 记事本保存的文件），
+// 非法 UTF-8 字节按替换字符有损解码，尽量保住 token 等有效内容。
+fn decode_settings_text(bytes: &[u8]) -> String {
+    let bytes = match bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        Some(rest) => {
+            log_line("[xau-tray] settings file has a UTF-8 BOM, stripped");
+            rest
+        }
+        None => bytes,
+    };
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(err) => {
+            log_line(&format!(
+                "[xau-tray] settings file is not valid UTF-8 ({err}), decoding lossily"
+            ));
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+    }
+}
+
 // 保存设置到本地磁盘（应用数据目录）。
+// 先写入临时文件再原子重命名，避免并发读取到写了一半的文件。
 fn save_settings(app: &AppHandle, settings: &QuoteSettings) -> Result<(), String> {
     let path = settings_file_path(app)?;
+    write_settings_file(&path, settings)
+}
+
+// 将设置写入指定路径。
+fn write_settings_file(path: &std::path::Path, settings: &QuoteSettings) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
-    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| e.to_string())
+    // 来自环境变量的 token 不落盘。
+    let mut settings = settings.clone();
+    if env_token().is_some_and(|token| token == settings.token) {
+        settings.token.clear();
+    }
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    // 串行化写入，防止多次保存争用同一个临时文件。
+    let _guard = lock_or_recover(&SETTINGS_SAVE_LOCK);
+    write_file_atomically(path, &content)
 }
 
-// 规范化设置：去重、补默认值、纠正非法输入。
-fn normalize_settings(mut settings: QuoteSettings) -> QuoteSettings {
-    // 将 token 文本按行归一化并写回，保持一致存储格式。
-    let tokens = parse_tokens(&settings.token);
-    settings.token = tokens.join("\n");
+// 设置文件写入锁：保存时持有，退出前获取一次以等待进行中的保存完成。
+static SETTINGS_SAVE_LOCK: Mutex<()> = Mutex::new(());
 
-    // 过滤空品类、去重并补充显示名称。
-    let mut seen = HashSet::new();
-    let mut symbols = Vec::new();
-    for mut symbol in settings.symbols.drain(..) {
-        let code = symbol.code.trim().to_string();
-        if code.is_empty() || seen.contains(&code) {
-            continue;
+// 等待进行中的设置保存完成后退出应用。
+fn exit_after_pending_save(app: &AppHandle) {
+    // 窗口开着时直接退出不会经过关闭事件，这里同样记录窗口位置与大小。
+    if let Err(err) = save_window_geometry(app) {
+        log_line(&format!("[xau-tray] save window geometry failed: {err}"));
+    }
+    drop(SETTINGS_SAVE_LOCK.lock());
+    app.exit(0);
+}
+
+// 先写入同目录的临时文件再重命名，保证读取方不会看到写了一半的内容。
+fn write_file_atomically(path: &std::path::Path, content: &str) -> Result<(), String> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        e.to_string()
+    })
+}
+
+// 检查应用数据目录是否可写：写入并删除一个临时文件，失败时返回错误信息。
+fn probe_data_dir_writable(app: &AppHandle) -> Result<(), String> {
+    let base = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+    let path = base.join(DATA_DIR_PROBE_FILE);
+    fs::write(&path, b"ok").map_err(|e| e.to_string())?;
+    fs::remove_file(&path).map_err(|e| e.to_string())
+}
+
+// 退出前保存的轮询状态：最新价格、趋势与错误信息，便于重启后排查。
+#[derive(Serialize)]
+struct PollStateSnapshot<'a> {
+    saved_at: String,
+    prices: &'a HashMap<String, f64>,
+    trends: &'a HashMap<String, String>,
+    last_error: Option<&'a FetchError>,
+    consecutive_failures: u32,
+}
+
+// 将轮询状态写入应用数据目录。
+fn save_poll_state(app: &AppHandle, snapshot: &PollStateSnapshot) -> Result<(), String> {
+    let base = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+    write_file_atomically(&base.join(POLL_STATE_FILE), &content)
+}
+
+// 设置窗口的位置与大小（物理像素）。
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+// 显示器区域：左上角位置与大小（物理像素）。
+type MonitorRect = (tauri::PhysicalPosition<i32>, tauri::PhysicalSize<u32>);
+
+// 保存设置窗口的位置与大小，关闭（隐藏）窗口与退出应用时调用。
+fn save_window_geometry(app: &AppHandle) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "main window not found".to_string())?;
+    let position = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.inner_size().map_err(|e| e.to_string())?;
+    let geometry = WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    let base = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(&geometry).map_err(|e| e.to_string())?;
+    write_file_atomically(&base.join(WINDOW_STATE_FILE), &content)
+}
+
+// 窗口左上角不在任何显示器上时（如外接显示器已断开），移到备用显示器内并尽量完整显示。
+fn clamp_to_monitors(
+    geometry: WindowGeometry,
+    monitors: &[MonitorRect],
+    fallback: Option<MonitorRect>,
+) -> WindowGeometry {
+    let visible = monitors.iter().any(|(position, size)| {
+        geometry.x >= position.x
+            && geometry.x < position.x + size.width as i32
+            && geometry.y >= position.y
+            && geometry.y < position.y + size.height as i32
+    });
+    let Some((position, size)) = fallback.filter(|_| !visible) else {
+        return geometry;
+    };
+    let max_x = position.x + size.width.saturating_sub(geometry.width) as i32;
+    let max_y = position.y + size.height.saturating_sub(geometry.height) as i32;
+    WindowGeometry {
+        x: geometry.x.clamp(position.x, max_x),
+        y: geometry.y.clamp(position.y, max_y),
+        ..geometry
+    }
+}
+
+// 启动时恢复设置窗口的位置与大小（窗口此时尚未显示），没有保存记录时保持默认。
+fn restore_window_geometry(app: &AppHandle) {
+    let Some(win) = app.get_webview_window("main") else {
+        return;
+    };
+    let Some(geometry) = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .and_then(|base| fs::read_to_string(base.join(WINDOW_STATE_FILE)).ok())
+        .and_then(|content| serde_json::from_str::<WindowGeometry>(&content).ok())
+    else {
+        return;
+    };
+    let monitors: Vec<MonitorRect> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| (*monitor.position(), *monitor.size()))
+        .collect();
+    let primary = app
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .map(|monitor| (*monitor.position(), *monitor.size()));
+    let geometry = clamp_to_monitors(geometry, &monitors, primary.or(monitors.first().copied()));
+    let _ = win.set_size(tauri::PhysicalSize {
+        width: geometry.width,
+        height: geometry.height,
+    });
+    let _ = win.set_position(tauri::PhysicalPosition {
+        x: geometry.x,
+        y: geometry.y,
+    });
+}
+
+// 退出信号：菜单点击退出时置位并唤醒轮询任务，由轮询任务保存状态后退出应用。
+#[derive(Default)]
+struct ShutdownSignal {
+    requested: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+impl ShutdownSignal {
+    fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_one();
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    // 唤醒睡眠中的轮询任务，但不请求退出。
+    fn wake(&self) {
+        self.notify.notify_one();
+    }
+
+    // 可被退出信号提前唤醒的睡眠。
+    async fn sleep(&self, duration: Duration) {
+        let _ = tokio::time::timeout(duration, self.notify.notified()).await;
+    }
+}
+
+// 探测行情接口主机是否可以建立 TCP 连接，用于判断网络是否已恢复。
+async fn probe_connectivity() -> bool {
+    let connect = tokio::net::TcpStream::connect(CONNECTIVITY_PROBE_ADDR);
+    let timeout = Duration::from_secs(CONNECTIVITY_PROBE_TIMEOUT_SECONDS);
+    matches!(tokio::time::timeout(timeout, connect).await, Ok(Ok(_)))
+}
+
+// 请求退出：通知轮询任务收尾；若轮询任务迟迟未退出（如请求卡住），超时后强制退出。
+fn request_shutdown(app: &AppHandle) {
+    app.state::<AppState>().shutdown.request();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(SHUTDOWN_GRACE_SECONDS)).await;
+        exit_after_pending_save(&app);
+    });
+}
+
+// 监听进程信号（Ctrl+C、Unix 下的 SIGTERM），收到后走与退出菜单相同的流程，
+// 便于以服务方式运行时被进程管理器正常停止。
+fn start_signal_listener(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut terminate = match signal(SignalKind::terminate()) {
+                Ok(terminate) => terminate,
+                Err(err) => {
+                    log_line(&format!("[xau-tray] install SIGTERM handler failed: {err}"));
+                    return;
+                }
+            };
+            let ctrl_c = tokio::signal::ctrl_c();
+            tokio::pin!(ctrl_c);
+            tokio::select! {
+                _ = terminate.recv() => log_line("[xau-tray] received SIGTERM"),
+                _ = &mut ctrl_c => log_line("[xau-tray] received Ctrl+C"),
+            }
         }
-        seen.insert(code.clone());
-        let label = symbol.label.trim().to_string();
-        symbol.code = code.clone();
-        symbol.label = if label.is_empty() { code.clone() } else { label };
-        symbols.push(symbol);
+        #[cfg(not(unix))]
+        {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            log_line("[xau-tray] received Ctrl+C");
+        }
+        request_shutdown(&app);
+    });
+}
+
+// 轮播一圈的时长短于刷新间隔时，会多次轮播到同一份数据，返回提示文案。
+fn rotation_warning(settings: &QuoteSettings, locale: &str) -> Option<String> {
+    if settings.display_mode != DisplayMode::Rotate || settings.symbols.is_empty() {
+        return None;
     }
+    let cycle_seconds = settings.rotate_seconds * settings.symbols.len() as u64;
+    if cycle_seconds >= settings.refresh_seconds {
+        return None;
+    }
+    Some(
+        tr(locale, "rotation_warning")
+            .replace("{cycle}", &cycle_seconds.to_string())
+            .replace("{refresh}", &settings.refresh_seconds.to_string()),
+    )
+}
 
-    // 如果用户清空了品类，则按 API 类型回填默认列表。
-    if symbols.is_empty() {
-        symbols = match settings.api_type {
-            ApiType::Commodity => default_symbols(),
-            ApiType::Stock => default_stock_symbols(),
-        };
+// 规范化设置：去重、补默认值、纠正非法输入。
+fn normalize_settings(mut settings: QuoteSettings) -> QuoteSettings {
+    // 将 token 文本按行归一化并写回，保持一致存储格式。
+    let tokens = parse_tokens(&settings.token);
+    settings.token = tokens.join("\n");
+
+    let mut symbols = normalize_symbols(std::mem::take(&mut settings.symbols));
+
+    // 如果用户清空了品类，则按 API 类型回填默认列表；允许空列表时保持为空，由轮询任务提示用户添加品类。
+    if symbols.is_empty() && !settings.allow_empty_symbols {
+        symbols = default_symbols_for(settings.api_type);
     }
 
     settings.symbols = symbols;
@@ -262,6 +1374,106 @@ fn normalize_settings(mut settings: QuoteSettings) -> QuoteSettings {
     settings.refresh_seconds = default_refresh_seconds();
     // 轮播间隔限制在合理范围内。
     settings.rotate_seconds = settings.rotate_seconds.clamp(ROTATE_MIN_SECONDS, 3600);
+    // 名称模板至少包含一个占位符，否则回退为默认模板。
+    if !settings.label_template.contains("{label}") && !settings.label_template.contains("{code}") {
+        settings.label_template = default_label_template();
+    }
+    // 备用地址只保留 http(s) 地址，去掉末尾的 "/"、重复项与主地址。
+    let mut endpoints_seen = HashSet::new();
+    settings.endpoints = settings
+        .endpoints
+        .iter()
+        .map(|endpoint| endpoint.trim().trim_end_matches('/').to_string())
+        .filter(|endpoint| endpoint.starts_with("https://") || endpoint.starts_with("http://"))
+        .filter(|endpoint| endpoint != API_BASE_URL && endpoints_seen.insert(endpoint.clone()))
+        .collect();
+    // 标题平滑系数限制在 (0, 1]，非法值视为关闭平滑。
+    settings.smoothing_alpha = settings
+        .smoothing_alpha
+        .filter(|alpha| alpha.is_finite() && *alpha > 0.0)
+        .map(|alpha| alpha.min(1.0));
+    // 模拟数据模式仅用于开发调试，release 构建中始终关闭。
+    if !cfg!(debug_assertions) {
+        settings.mock_mode = false;
+    }
+    // 最大退避限制在 30 秒 ~ 1 小时，首次退避倍数限制在 1~10。
+    settings.max_backoff_seconds = settings.max_backoff_seconds.clamp(30, 3600);
+    settings.backoff_multiplier = settings.backoff_multiplier.clamp(1, 10);
+    // 跳变阈值必须为正数，否则视为关闭跳变检测。
+    settings.max_jump_percent = settings
+        .max_jump_percent
+        .filter(|limit| limit.is_finite() && *limit > 0.0);
+    // 标题宽度上限至少为 2（一个字符加省略号），否则视为不限制。
+    settings.max_title_chars = settings.max_title_chars.filter(|width| *width >= 2);
+    // 标题最小变动阈值不能为负，非法值视为始终更新。
+    if !settings.min_title_change.is_finite() || settings.min_title_change < 0.0 {
+        settings.min_title_change = 0.0;
+    }
+    // 均线周期限制在 2~200，小于 2 时视为关闭。
+    settings.show_ma = settings
+        .show_ma
+        .filter(|period| *period >= 2)
+        .map(|period| period.min(200));
+    // 趋势基准偏移最多 240 根 K 线（1 分钟 K 线即 4 小时）。
+    settings.baseline_offset = settings.baseline_offset.min(240);
+    // 固定宽度标题的整数位数限制在 1~10。
+    settings.title_integer_digits = settings.title_integer_digits.clamp(1, 10);
+    // 标题分隔符不能为空（保留首尾空格，如 " | "），为空时回退为单个空格。
+    if settings.title_separator.is_empty() {
+        settings.title_separator = default_title_separator();
+    }
+    // 无数据占位符去掉首尾空白，为空时恢复默认。
+    settings.no_data_placeholder = settings.no_data_placeholder.trim().to_string();
+    if settings.no_data_placeholder.is_empty() {
+        settings.no_data_placeholder = default_no_data_placeholder();
+    }
+    // 语言为空时回退为跟随系统。
+    settings.locale = settings.locale.trim().to_string();
+    if settings.locale.is_empty() {
+        settings.locale = default_locale();
+    }
+    // 自选列表：各列表内去重，至少保留一个列表，当前列表的内容以 symbols 为准；默认名称随语言。
+    normalize_watchlists(&mut settings);
+    // 轮播过快只做提示，不修改配置。
+    if let Some(warning) = rotation_warning(&settings, effective_locale(&settings.locale)) {
+        log_line(&format!("[xau-tray] settings hint: {warning}"));
+    }
+    // 平盘阈值限制在 0%~10%，非法值视为 0。
+    settings.flat_threshold_percent = if settings.flat_threshold_percent.is_finite() {
+        settings.flat_threshold_percent.clamp(0.0, 10.0)
+    } else {
+        0.0
+    };
+    // 错误/非最新提示符去除首尾空白并限制长度，空字符串表示不显示。
+    settings.error_indicator = normalize_indicator(&settings.error_indicator);
+    settings.stale_indicator = normalize_indicator(&settings.stale_indicator);
+    // 焦点刷新间隔不低于 2 秒，且必须短于批量刷新间隔才有意义。
+    settings.focus_refresh_seconds = settings
+        .focus_refresh_seconds
+        .map(|seconds| seconds.max(FOCUS_REFRESH_MIN_SECONDS))
+        .filter(|seconds| *seconds < settings.refresh_seconds);
+    // 动量窗口最多 100 次刷新，0 表示与开盘价比较。
+    settings.momentum_window = settings.momentum_window.min(100);
+    // tooltip 行数限制在合理范围内。
+    settings.max_tooltip_lines = settings.max_tooltip_lines.clamp(3, 200);
+    // 指标端口为 0 时视为关闭。
+    settings.metrics_port = settings.metrics_port.filter(|port| *port != 0);
+    // 静默时段的小时需在 0..24 内，且起止不能相同。
+    settings.quiet_hours = settings
+        .quiet_hours
+        .filter(|(start, end)| *start < 24 && *end < 24 && start != end);
+    // 组合成分必须在品类列表中且权重为正，同一代码只保留第一次出现。
+    let mut portfolio_seen = HashSet::new();
+    settings.portfolio = std::mem::take(&mut settings.portfolio)
+        .into_iter()
+        .map(|(code, weight)| (code.trim().to_string(), weight))
+        .filter(|(code, weight)| {
+            weight.is_finite()
+                && *weight > 0.0
+                && settings.symbols.iter().any(|s| &s.code == code)
+                && portfolio_seen.insert(code.clone())
+        })
+        .collect();
 
     // 固定展示模式时，确保 fixed_symbol 在当前列表中存在。
     if settings.display_mode == DisplayMode::Fixed {
@@ -271,54 +1483,231 @@ fn normalize_settings(mut settings: QuoteSettings) -> QuoteSettings {
             .unwrap_or_default()
             .trim()
             .to_string();
-        let exists = settings.symbols.iter().any(|s| s.code == fixed);
-        settings.fixed_symbol = Some(if exists {
-            fixed
+        let exists = settings.symbols.iter().any(|s| s.code == fixed)
+            || (fixed == PORTFOLIO_CODE && !settings.portfolio.is_empty());
+        settings.fixed_symbol = if exists {
+            Some(fixed)
         } else {
-            settings.symbols[0].code.clone()
-        });
+            settings.symbols.first().map(|symbol| symbol.code.clone())
+        };
     }
 
     settings
 }
 
-// 将 token 输入按行切分并清洗，过滤空行。
+// 过滤空品类、去重并补充显示名称。
+fn normalize_symbols(items: Vec<SymbolItem>) -> Vec<SymbolItem> {
+    let mut seen = HashSet::new();
+    let mut symbols = Vec::new();
+    for mut symbol in items {
+        let code = symbol.code.trim().to_string();
+        if code.is_empty() || seen.contains(&code) {
+            continue;
+        }
+        seen.insert(code.clone());
+        let label = symbol.label.trim().to_string();
+        symbol.code = code.clone();
+        symbol.label = if label.is_empty() { code.clone() } else { label };
+        // 混合品类的成分代码去空、去重，为空时视为普通品类。
+        symbol.blend = symbol.blend.take().and_then(|parts| {
+            let mut parts_seen = HashSet::new();
+            let parts: Vec<String> = parts
+                .iter()
+                .map(|part| part.trim().to_string())
+                .filter(|part| !part.is_empty() && parts_seen.insert(part.clone()))
+                .collect();
+            if parts.is_empty() {
+                None
+            } else {
+                Some(parts)
+            }
+        });
+        // 货币符号去除空白，为空或超过 3 个字符时忽略。
+        symbol.currency_symbol = symbol
+            .currency_symbol
+            .take()
+            .map(|prefix| prefix.trim().to_string())
+            .filter(|prefix| !prefix.is_empty() && prefix.chars().count() <= 3);
+        // 入场价必须为正数，否则视为未设置。
+        symbol.entry_price = symbol
+            .entry_price
+            .filter(|price| price.is_finite() && *price > 0.0);
+        // 交易时段的时间或时区无法解析时忽略，按全天交易处理。
+        symbol.session = symbol.session.take().and_then(|mut session| {
+            session.open = session.open.trim().to_string();
+            session.close = session.close.trim().to_string();
+            session.timezone = session.timezone.trim().to_string();
+            if parse_session(&session).is_some() {
+                Some(session)
+            } else {
+                log_line(&format!(
+                    "[xau-tray] invalid session for {}, ignored",
+                    symbol.code
+                ));
+                None
+            }
+        });
+        symbols.push(symbol);
+    }
+    // 至少保留一个启用的品类：全部停用时重新启用第一个。
+    if !symbols.iter().any(|symbol| symbol.enabled) {
+        if let Some(first) = symbols.first_mut() {
+            first.enabled = true;
+        }
+    }
+    symbols
+}
+
+// 规范化自选列表：名称去空白（为空时按序号命名）、列表内品类去重；
+// 没有列表时以当前品类创建默认列表，当前列表下标越界时回到第一个。
+fn normalize_watchlists(settings: &mut QuoteSettings) {
+    let locale = effective_locale(&settings.locale).to_string();
+    if settings.watchlists.is_empty() {
+        settings.watchlists.push(Watchlist {
+            name: tr(&locale, "watchlist_default").to_string(),
+            symbols: Vec::new(),
+        });
+    }
+    if settings.active_watchlist >= settings.watchlists.len() {
+        settings.active_watchlist = 0;
+    }
+    for (index, list) in settings.watchlists.iter_mut().enumerate() {
+        list.name = list.name.trim().to_string();
+        if list.name.is_empty() {
+            list.name =
+                tr(&locale, "watchlist_numbered").replace("{index}", &(index + 1).to_string());
+        }
+        list.symbols = normalize_symbols(std::mem::take(&mut list.symbols));
+    }
+    settings.watchlists[settings.active_watchlist].symbols = settings.symbols.clone();
+}
+
+// 构造实际请求的 code 列表：普通品类请求自身，混合品类请求其成分代码，跳过停用品类，保持顺序并去重。
+fn request_codes(symbols: &[SymbolItem]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut codes = Vec::new();
+    for symbol in symbols.iter().filter(|symbol| symbol.enabled) {
+        let parts = match symbol.blend.as_ref() {
+            Some(parts) => parts.as_slice(),
+            None => std::slice::from_ref(&symbol.code),
+        };
+        for code in parts {
+            if seen.insert(code.clone()) {
+                codes.push(code.clone());
+            }
+        }
+    }
+    codes
+}
+
+// 计算混合品类的均值行情，只使用有数据的成分；返回 (行情, 是否缺少部分成分)。
+fn blend_quote(parts: &[String], map: &HashMap<String, Quote>) -> Option<(Quote, bool)> {
+    let quotes: Vec<&Quote> = parts.iter().filter_map(|code| map.get(code)).collect();
+    if quotes.is_empty() {
+        return None;
+    }
+    let count = quotes.len() as f64;
+    let quote = Quote {
+        price: quotes.iter().map(|q| q.price).sum::<f64>() / count,
+        timestamp: quotes.iter().map(|q| q.timestamp).max().unwrap_or(0),
+        open: quotes.iter().map(|q| q.open).sum::<f64>() / count,
+        volume: None,
+    };
+    Some((quote, quotes.len() < parts.len()))
+}
+
+// 计算组合指数：各成分相对开盘价涨跌幅的加权和，以 100 为基准；
+// 缺少部分成分时按有数据的成分重新归一化权重，返回 (行情, 是否缺少部分成分)。
+fn portfolio_quote(
+    weights: &[(String, f64)],
+    map: &HashMap<String, Quote>,
+) -> Option<(Quote, bool)> {
+    let mut total_weight = 0.0;
+    let mut change = 0.0;
+    let mut timestamp = 0;
+    let mut available = 0;
+    for (code, weight) in weights {
+        let Some(quote) = map.get(code).filter(|quote| quote.open != 0.0) else {
+            continue;
+        };
+        total_weight += weight;
+        change += weight * (quote.price / quote.open - 1.0);
+        timestamp = timestamp.max(quote.timestamp);
+        available += 1;
+    }
+    if available == 0 || total_weight <= 0.0 {
+        return None;
+    }
+    let quote = Quote {
+        price: 100.0 * (1.0 + change / total_weight),
+        timestamp,
+        open: 100.0,
+        volume: None,
+    };
+    Some((quote, available < weights.len()))
+}
+
+// 组合指数的虚拟品类；成分代码放在 blend 中，随批量请求一起拉取。
+fn portfolio_symbol(weights: &[(String, f64)], locale: &str) -> SymbolItem {
+    SymbolItem {
+        code: PORTFOLIO_CODE.to_string(),
+        label: tr(locale, "portfolio").to_string(),
+        blend: Some(weights.iter().map(|(code, _)| code.clone()).collect()),
+        ..SymbolItem::default()
+    }
+}
+
+// 读取环境变量中的 token，与设置相同支持换行、逗号或分号分隔，归一化为与设置一致的按行格式。
+fn env_token() -> Option<String> {
+    let value = std::env::var(TOKEN_ENV_VAR).ok()?;
+    let tokens = parse_tokens(&value);
+    if tokens.is_empty() {
+        None
+    } else {
+        Some(tokens.join("\n"))
+    }
+}
+
+// 将 token 输入按换行、逗号或分号切分并清洗，过滤空段。
 fn parse_tokens(token: &str) -> Vec<String> {
     token
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .map(|line| line.to_string())
+        .split(['\n', ',', ';'])
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_string())
         .collect()
 }
 
-// 发起批量行情请求，并返回 {code -> (price, timestamp, open)} 映射。
-async fn fetch_batch_quotes(
+// 构造批量 K 线请求的 URL（含 token）与请求体。
+fn build_batch_request(
+    base_url: &str,
     token: &str,
     codes: &[String],
     api_type: ApiType,
-    use_system_proxy: bool,
-) -> Result<HashMap<String, (f64, u64, f64)>, FetchError> {
+    kline_type: u8,
+    kline_num: u32,
+) -> Result<(reqwest::Url, serde_json::Value), FetchError> {
     // 根据品类类型选择接口。
-    let endpoint = match api_type {
-        ApiType::Commodity => "https://quote.alltick.io/quote-b-api/batch-kline",
-        ApiType::Stock => "https://quote.alltick.io/quote-stock-b-api/batch-kline",
+    let path = match api_type {
+        ApiType::Commodity => "/quote-b-api/batch-kline",
+        ApiType::Stock => "/quote-stock-b-api/batch-kline",
     };
-    let mut url = reqwest::Url::parse(endpoint).map_err(|e| FetchError::new(e.to_string()))?;
+    let mut url = reqwest::Url::parse(&format!("{base_url}{path}"))
+        .map_err(|e| FetchError::new(e.to_string()))?;
 
     // 通过 query 参数传 token。
     url.query_pairs_mut().append_pair("token", token);
 
-    // 构造请求体，批量请求每个 code 的最新一条 K 线。
+    // 构造请求体，批量请求每个 code 最新的 kline_num 条 K 线。
     let trace = uuid::Uuid::new_v4().to_string();
     let data_list: Vec<serde_json::Value> = codes
         .iter()
         .map(|code| {
             serde_json::json!({
                 "code": code,
-                "kline_type": 1,
+                "kline_type": kline_type,
                 "kline_timestamp_end": 0,
-                "query_kline_num": 1,
+                "query_kline_num": kline_num,
                 "adjust_type": 0
             })
         })
@@ -328,33 +1717,245 @@ async fn fetch_batch_quotes(
         "trace": trace,
         "data": { "data_list": data_list }
     });
+    Ok((url, body))
+}
+
+// 查询单个品类的最新成交价，仅用于当前展示品类以节省配额。
+async fn fetch_last_tick(
+    token: &str,
+    code: &str,
+    api_type: ApiType,
+    use_system_proxy: bool,
+    endpoints: &[String],
+) -> Result<f64, FetchError> {
+    let payload = fetch_tick_payload(
+        token,
+        std::slice::from_ref(&code.to_string()),
+        api_type,
+        use_system_proxy,
+        endpoints,
+    )
+    .await?;
+    payload
+        .data
+        .tick_list
+        .iter()
+        .find(|tick| tick.code == code)
+        .and_then(|tick| parse_api_number(code, "price", &tick.price))
+        .ok_or_else(|| FetchError::new(format!("no tick for {code}")))
+}
+
+// 轻量批量请求：只拉取各品类的最新成交价，响应比 K 线小得多；
+// 没有开盘价与成交量，开盘价以最新价代替，只在当前显示配置不依赖它们时使用。
+async fn fetch_batch_ticks(
+    token: &str,
+    codes: &[String],
+    api_type: ApiType,
+    use_system_proxy: bool,
+    endpoints: &[String],
+) -> Result<(HashMap<String, Quote>, FetchMeta), FetchError> {
+    let request_started = Instant::now();
+    let payload = fetch_tick_payload(token, codes, api_type, use_system_proxy, endpoints).await?;
+    let meta = FetchMeta {
+        ret: payload.ret,
+        items: payload.data.tick_list.len(),
+        elapsed_ms: request_started.elapsed().as_millis(),
+    };
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut map = HashMap::new();
+    for tick in payload.data.tick_list {
+        let Some(price) = parse_api_number(&tick.code, "price", &tick.price) else {
+            continue;
+        };
+        // tick_time 为毫秒时间戳，缺失时以当前时间代替。
+        let timestamp = tick
+            .tick_time
+            .as_deref()
+            .and_then(|value| parse_api_timestamp(&tick.code, value))
+            .map_or(now, |ms| ms / 1000);
+        map.insert(
+            tick.code,
+            Quote {
+                price,
+                timestamp,
+                open: price,
+                volume: None,
+            },
+        );
+    }
+    Ok((map, meta))
+}
+
+// 当前显示配置是否需要 K 线数据：开盘价用于涨跌、绝对涨跌额与最大波动，成交量用于 tooltip；
+// 只有全部品类都按入场价计算涨跌且不展示这些信息时，才可以改用轻量接口。
+fn needs_kline_data(settings: &QuoteSettings) -> bool {
+    let all_from_entry = settings.trend_from_entry
+        && settings
+            .symbols
+            .iter()
+            .all(|symbol| symbol.entry_price.is_some() && symbol.blend.is_none());
+    !all_from_entry
+        || settings.show_abs_change
+        || settings.show_volume
+        || settings.display_mode == DisplayMode::TopMover
+}
+
+// 请求 tick 接口，返回 ret 为 200 的响应；与批量请求相同，主地址连接失败或超时时依次尝试备用地址。
+async fn fetch_tick_payload(
+    token: &str,
+    codes: &[String],
+    api_type: ApiType,
+    use_system_proxy: bool,
+    endpoints: &[String],
+) -> Result<TickResp, FetchError> {
+    let path = match api_type {
+        ApiType::Commodity => "/quote-b-api/trade-tick",
+        ApiType::Stock => "/quote-stock-b-api/trade-tick",
+    };
+    // tick 接口通过 query 参数同时传 token 与 JSON 查询体。
+    let symbol_list: Vec<serde_json::Value> = codes
+        .iter()
+        .map(|code| serde_json::json!({ "code": code }))
+        .collect();
+    let query = serde_json::json!({
+        "trace": uuid::Uuid::new_v4().to_string(),
+        "data": { "symbol_list": symbol_list }
+    });
+
+    let proxy_setting = request_proxy_setting(use_system_proxy);
+    let client = build_http_client(proxy_setting.as_ref()).map_err(FetchError::new)?;
+    let base_urls = endpoint_candidates(endpoints);
+    let mut attempt = 0;
+    let (status, body_text) = loop {
+        let base_url = base_urls[attempt];
+        let mut url = reqwest::Url::parse(&format!("{base_url}{path}"))
+            .map_err(|e| FetchError::new(e.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("token", token)
+            .append_pair("query", &query.to_string());
+        let result = async {
+            let resp = client.get(url).send().await?;
+            let status = resp.status();
+            Ok::<_, reqwest::Error>((status, resp.text().await?))
+        }
+        .await;
+        match result {
+            Ok(response) => break response,
+            Err(e) => {
+                let err = FetchError::new(format_reqwest_error(&e));
+                attempt += 1;
+                if !is_connection_failure(&err.detail) || attempt >= base_urls.len() {
+                    return Err(err);
+                }
+                log_line(&format!(
+                    "[xau-tray] {base_url} unreachable, fall back to {}",
+                    base_urls[attempt]
+                ));
+            }
+        }
+    };
+    if !status.is_success() {
+        return Err(FetchError::new(format!(
+            "http status {status} body={body_text}"
+        )));
+    }
+    let payload = serde_json::from_str::<TickResp>(&body_text)
+        .map_err(|e| FetchError::decode(&e, &body_text))?;
+    if payload.ret != 200 {
+        return Err(FetchError::with_msg(payload.ret, payload.msg));
+    }
+    Ok(payload)
+}
+
+// 行情接口的候选地址：主地址在前，其后依次为设置中的备用地址。
+fn endpoint_candidates(endpoints: &[String]) -> Vec<&str> {
+    std::iter::once(API_BASE_URL)
+        .chain(endpoints.iter().map(String::as_str))
+        .collect()
+}
 
-    // 根据配置决定是否启用系统代理。
+// 根据配置决定是否启用系统代理，并记录决策日志。
+fn request_proxy_setting(use_system_proxy: bool) -> Option<ProxySetting> {
     let proxy_setting = if use_system_proxy {
         system_proxy_setting()
     } else {
         None
     };
     log_proxy_decision(proxy_setting.as_ref());
-    let request_started = Instant::now();
-    let payload = match send_batch_request(proxy_setting.as_ref(), url, &body).await {
-        Ok(payload) => {
-            let elapsed_ms = request_started.elapsed().as_millis();
-            log_line(&format!(
-                "[xau-tray] request result: success ret={} items={} elapsed_ms={}",
-                payload.ret,
-                payload.data.kline_list.len(),
-                elapsed_ms
-            ));
-            payload
-        }
-        Err(err) => {
-            let elapsed_ms = request_started.elapsed().as_millis();
-            log_line(&format!(
-                "[xau-tray] request result: failed error={} elapsed_ms={}",
-                err, elapsed_ms
-            ));
-            return Err(FetchError::new(err));
+    proxy_setting
+}
+
+// 推送连接不经过代理：启用了系统代理且检测到代理时不建立推送，回退为轮询。
+fn stream_blocked_by_proxy(settings: &QuoteSettings) -> bool {
+    settings.transport == Transport::Stream
+        && settings.use_system_proxy
+        && system_proxy_setting().is_some()
+}
+
+// 将 URL 中的 token 参数替换为 ***，用于日志与调试输出。
+fn redact_token_url(url: &reqwest::Url) -> String {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if key == "token" {
+                "***".to_string()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    let mut redacted = url.clone();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted.to_string()
+}
+
+// 发起批量 K 线请求，接口返回业务错误时转换为 FetchError。
+async fn fetch_kline_payload(
+    token: &str,
+    codes: &[String],
+    api_type: ApiType,
+    use_system_proxy: bool,
+    endpoints: &[String],
+    kline_type: u8,
+    kline_num: u32,
+) -> Result<BatchResp, FetchError> {
+    let proxy_setting = request_proxy_setting(use_system_proxy);
+    // 先请求主地址，连接失败或超时时依次尝试备用地址；其他错误不切换。
+    let base_urls = endpoint_candidates(endpoints);
+    let mut attempt = 0;
+    let payload = loop {
+        let base_url = base_urls[attempt];
+        let (url, body) =
+            build_batch_request(base_url, token, codes, api_type, kline_type, kline_num)?;
+        let request_started = Instant::now();
+        match send_batch_request(proxy_setting.as_ref(), url, &body).await {
+            Ok(mut payload) => {
+                let elapsed_ms = request_started.elapsed().as_millis();
+                log_line(&format!(
+                    "[xau-tray] request result: success ret={} items={} elapsed_ms={}",
+                    payload.ret,
+                    payload.data.kline_list.len(),
+                    elapsed_ms
+                ));
+                payload.elapsed_ms = elapsed_ms;
+                break payload;
+            }
+            Err(err) => {
+                let elapsed_ms = request_started.elapsed().as_millis();
+                log_line(&format!(
+                    "[xau-tray] request result: failed error={} elapsed_ms={}",
+                    err.detail, elapsed_ms
+                ));
+                attempt += 1;
+                if !is_connection_failure(&err.detail) || attempt >= base_urls.len() {
+                    return Err(err);
+                }
+                log_line(&format!(
+                    "[xau-tray] {base_url} unreachable, fall back to {}",
+                    base_urls[attempt]
+                ));
+            }
         }
     };
     // API 层返回错误时，将 ret 与 msg 作为业务错误返回。
@@ -364,47 +1965,676 @@ async fn fetch_batch_quotes(
             payload.ret,
             payload.data.kline_list.len()
         ));
-        return Err(FetchError::with_msg(
-            format!("api ret={}", payload.ret),
-            payload.msg.clone(),
-        ));
+        return Err(FetchError::with_msg(payload.ret, payload.msg.clone()));
     }
+    Ok(payload)
+}
 
-    // 提取需要的价格与开盘价，构造查找表。
-    let mut map = HashMap::new();
-    for item in payload.data.kline_list {
-        if let Some(kline) = item.kline_data.get(0) {
-            if let (Ok(price), Ok(ts), Ok(open)) = (
-                kline.close_price.parse::<f64>(),
-                kline.timestamp.parse::<u64>(),
-                kline.open_price.parse::<f64>(),
-            ) {
-                map.insert(item.code, (price, ts, open));
-            }
+// 宽松解析接口返回的数值：去掉千分位逗号与空白，支持科学计数法；无法解析时记录日志。
+fn parse_api_number(code: &str, field: &str, value: &str) -> Option<f64> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| *c != ',' && !c.is_whitespace())
+        .collect();
+    match cleaned.parse::<f64>() {
+        Ok(number) if number.is_finite() => Some(number),
+        _ => {
+            log_line(&format!(
+                "[xau-tray] unparsable {field} for {code}: {value:?}"
+            ));
+            None
         }
     }
-    Ok(map)
 }
 
-// 用于在 tooltip 中展示错误细节与接口 msg。
-#[derive(Clone, Debug)]
-struct FetchError {
-    detail: String,
-    msg: Option<String>,
+// 解析接口返回的秒级时间戳，无法解析时记录日志。
+fn parse_api_timestamp(code: &str, value: &str) -> Option<u64> {
+    let parsed = value.trim().parse::<u64>().ok();
+    if parsed.is_none() {
+        log_line(&format!(
+            "[xau-tray] unparsable timestamp for {code}: {value:?}"
+        ));
+    }
+    parsed
 }
 
-impl FetchError {
-    fn new(detail: String) -> Self {
-        Self { detail, msg: None }
-    }
+// 推送连接的事件：连接成功、收到成交价，或连接结束（附带原因）。
+enum StreamEvent {
+    Connected,
+    Trade { code: String, price: f64 },
+    Closed(String),
+}
 
-    fn with_msg(detail: String, msg: Option<String>) -> Self {
-        Self { detail, msg }
+// 推送接口返回的消息：订阅应答带 ret/msg，成交价推送的 data 中带 code 与 price。
+#[derive(Deserialize)]
+struct StreamMessage {
+    #[serde(default)]
+    cmd_id: u32,
+    #[serde(default)]
+    ret: Option<i64>,
+    #[serde(default)]
+    msg: Option<String>,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+}
+
+// 推送接口地址：由行情接口地址换成 ws(s) 协议得到，token 通过查询参数传递。
+fn stream_url(base_url: &str, api_type: ApiType, token: &str) -> Result<reqwest::Url, String> {
+    let path = match api_type {
+        ApiType::Commodity => "/quote-b-ws-api",
+        ApiType::Stock => "/quote-stock-b-ws-api",
+    };
+    let base_url = if let Some(rest) = base_url.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = base_url.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        base_url.to_string()
+    };
+    reqwest::Url::parse_with_params(&format!("{base_url}{path}"), &[("token", token)])
+        .map_err(|e| e.to_string())
+}
+
+// 构造推送接口的请求文本。
+fn stream_command(cmd_id: u32, seq_id: u64, data: serde_json::Value) -> String {
+    serde_json::json!({
+        "cmd_id": cmd_id,
+        "seq_id": seq_id,
+        "trace": uuid::Uuid::new_v4().to_string(),
+        "data": data,
+    })
+    .to_string()
+}
+
+// 解析推送消息：返回成交价推送中的 (code, price)；应答 ret 非 200 时返回错误，其余消息忽略。
+fn parse_stream_message(text: &str) -> Result<Option<(String, f64)>, String> {
+    let Ok(message) = serde_json::from_str::<StreamMessage>(text) else {
+        log_line(&format!("[xau-tray] unparsable stream message: {text}"));
+        return Ok(None);
+    };
+    if let Some(ret) = message.ret.filter(|ret| *ret != 200) {
+        let msg = message.msg.unwrap_or_default();
+        return Err(format!("stream ret={ret} msg={msg}"));
+    }
+    if message.cmd_id != STREAM_CMD_TRADE_PUSH {
+        return Ok(None);
+    }
+    let Some(data) = message.data else {
+        return Ok(None);
+    };
+    let Some(code) = data.get("code").and_then(|code| code.as_str()) else {
+        return Ok(None);
+    };
+    let price = match data.get("price") {
+        Some(serde_json::Value::String(price)) => parse_api_number(code, "price", price),
+        Some(serde_json::Value::Number(price)) => price.as_f64(),
+        _ => None,
+    };
+    Ok(price.map(|price| (code.to_string(), price)))
+}
+
+// 连接推送接口并订阅最新成交价，收到的推送通过 sender 转发给轮询任务，直到连接出错或断开。
+// 连接与订阅超时、长时间收不到消息都按连接失败处理，由轮询任务回退为定时请求并稍后重连。
+// 推送连接不支持代理，检测到代理时轮询任务不会发起推送连接；
+// 主地址连接失败或超时时依次尝试备用地址，握手被拒绝等其他错误不切换。
+async fn run_price_stream(
+    token: String,
+    codes: Vec<String>,
+    api_type: ApiType,
+    endpoints: Vec<String>,
+    sender: tokio::sync::mpsc::UnboundedSender<StreamEvent>,
+) -> Result<(), String> {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+
+    let base_urls = endpoint_candidates(&endpoints);
+    let mut attempt = 0;
+    let socket = loop {
+        let base_url = base_urls[attempt];
+        let url = stream_url(base_url, api_type, &token)?;
+        let (err, fallback) = match tokio::time::timeout(
+            REQUEST_TIMEOUT,
+            tokio_tungstenite::connect_async(url.as_str()),
+        )
+        .await
+        {
+            Ok(Ok((socket, _))) => break socket,
+            Ok(Err(err)) => {
+                let fallback = matches!(err, WsError::Io(_));
+                (err.to_string(), fallback)
+            }
+            Err(_) => ("stream connect timed out".to_string(), true),
+        };
+        attempt += 1;
+        if !fallback || attempt >= base_urls.len() {
+            return Err(err);
+        }
+        log_line(&format!(
+            "[xau-tray] stream {base_url} unreachable, fall back to {}",
+            base_urls[attempt]
+        ));
+    };
+    let (mut write, mut read) = socket.split();
+    let symbol_list: Vec<serde_json::Value> = codes
+        .iter()
+        .map(|code| serde_json::json!({ "code": code }))
+        .collect();
+    let mut seq_id: u64 = 1;
+    let subscribe = stream_command(
+        STREAM_CMD_SUBSCRIBE_TRADE,
+        seq_id,
+        serde_json::json!({ "symbol_list": symbol_list }),
+    );
+    tokio::time::timeout(REQUEST_TIMEOUT, write.send(Message::text(subscribe)))
+        .await
+        .map_err(|_| "stream subscribe timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+    log_line(&format!(
+        "[xau-tray] stream connected: {} code(s)",
+        codes.len()
+    ));
+    if sender.send(StreamEvent::Connected).is_err() {
+        return Ok(());
+    }
+
+    let mut heartbeat = tokio::time::interval(Duration::from_secs(STREAM_HEARTBEAT_SECONDS));
+    // interval 的第一次 tick 立即完成，订阅后不需要马上发心跳。
+    heartbeat.tick().await;
+    let mut last_message = Instant::now();
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                if last_message.elapsed() >= Duration::from_secs(STREAM_IDLE_TIMEOUT_SECONDS) {
+                    return Err("stream idle timeout".to_string());
+                }
+                seq_id += 1;
+                let ping = stream_command(STREAM_CMD_HEARTBEAT, seq_id, serde_json::json!({}));
+                write
+                    .send(Message::text(ping))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            message = read.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(err)) => return Err(err.to_string()),
+                    None => return Err("stream closed by server".to_string()),
+                };
+                // 任何消息（包括心跳应答与 ping）都说明连接仍然存活。
+                last_message = Instant::now();
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Close(_) => return Err("stream closed by server".to_string()),
+                    _ => continue,
+                };
+                if let Some((code, price)) = parse_stream_message(text.as_str())? {
+                    // 轮询任务已退出时结束推送连接。
+                    if sender.send(StreamEvent::Trade { code, price }).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// 行情数据源：按代码批量获取 {code -> Quote} 映射与本次请求的元信息。
+// 只覆盖批量报价；tick、K 线明细、昨收、推送、调试与连通性检测仍直接请求 Alltick，
+// 模拟数据模式下这些附加请求全部跳过（见 alltick_extras_enabled）。
+trait QuoteProvider {
+    async fn fetch(
+        &self,
+        codes: &[String],
+        api_type: ApiType,
+    ) -> Result<(HashMap<String, Quote>, FetchMeta), FetchError>;
+}
+
+// Alltick 数据源：批量请求 K 线，主地址连接失败时依次尝试备用地址。
+// kline_type 为取价所用的 K 线周期；baseline_offset 大于 0 时多请求 offset 根 K 线，
+// open 改为最早一根的收盘价，趋势即相对 offset 个周期前。
+struct AlltickProvider<'a> {
+    token: &'a str,
+    use_system_proxy: bool,
+    endpoints: &'a [String],
+    kline_type: u8,
+    baseline_offset: u32,
+}
+
+impl QuoteProvider for AlltickProvider<'_> {
+    async fn fetch(
+        &self,
+        codes: &[String],
+        api_type: ApiType,
+    ) -> Result<(HashMap<String, Quote>, FetchMeta), FetchError> {
+        let payload = fetch_kline_payload(
+            self.token,
+            codes,
+            api_type,
+            self.use_system_proxy,
+            self.endpoints,
+            self.kline_type,
+            self.baseline_offset + 1,
+        )
+        .await?;
+        let meta = FetchMeta {
+            ret: payload.ret,
+            items: payload.data.kline_list.len(),
+            elapsed_ms: payload.elapsed_ms,
+        };
+
+        let quotes = parse_kline_quotes(payload.data.kline_list, self.baseline_offset);
+        Ok((quotes, meta))
+    }
+}
+
+// 从 K 线响应中提取价格、开盘价与成交量，构造 {code -> Quote} 查找表；数值无法解析的品类直接跳过，不会按 0 处理。
+// baseline_offset 大于 0 时 open 改为最早一根 K 线的收盘价。
+fn parse_kline_quotes(kline_list: Vec<BatchItem>, baseline_offset: u32) -> HashMap<String, Quote> {
+    let mut map = HashMap::new();
+    let by_time = |kline: &&ApiKline| kline.timestamp.trim().parse::<u64>().unwrap_or(0);
+    for item in kline_list {
+        if let Some(kline) = item.kline_data.iter().max_by_key(by_time) {
+            let open = if baseline_offset > 0 {
+                item.kline_data
+                    .iter()
+                    .min_by_key(by_time)
+                    .and_then(|oldest| {
+                        parse_api_number(&item.code, "close_price", &oldest.close_price)
+                    })
+            } else {
+                parse_api_number(&item.code, "open_price", &kline.open_price)
+            };
+            if let (Some(price), Some(ts), Some(open)) = (
+                parse_api_number(&item.code, "close_price", &kline.close_price),
+                parse_api_timestamp(&item.code, &kline.timestamp),
+                open,
+            ) {
+                // 成交量缺失或无法解析时不影响价格展示。
+                let volume = kline
+                    .volume
+                    .as_deref()
+                    .filter(|v| !v.trim().is_empty())
+                    .and_then(|v| parse_api_number(&item.code, "volume", v));
+                map.insert(
+                    item.code,
+                    Quote {
+                        price,
+                        timestamp: ts,
+                        open,
+                        volume,
+                    },
+                );
+            }
+        }
+    }
+    map
+}
+
+// 模拟数据源：不发起网络请求，价格随时间缓慢漂移，仅用于开发调试。
+struct MockProvider;
+
+impl QuoteProvider for MockProvider {
+    async fn fetch(
+        &self,
+        codes: &[String],
+        _api_type: ApiType,
+    ) -> Result<(HashMap<String, Quote>, FetchMeta), FetchError> {
+        Ok(mock_quotes(codes))
+    }
+}
+
+// 发起批量行情请求，按设置选择数据源，返回 {code -> Quote} 映射与本次请求的元信息。
+// 接口类型、代理、备用地址、数据源与模拟数据模式取自 settings。
+async fn fetch_batch_quotes(
+    token: &str,
+    codes: &[String],
+    settings: &QuoteSettings,
+    kline_type: u8,
+    baseline_offset: u32,
+) -> Result<(HashMap<String, Quote>, FetchMeta), FetchError> {
+    let api_type = settings.api_type;
+    if settings.mock_mode {
+        return MockProvider.fetch(codes, api_type).await;
+    }
+    match settings.provider {
+        Provider::Alltick => {
+            let provider = AlltickProvider {
+                token,
+                use_system_proxy: settings.use_system_proxy,
+                endpoints: &settings.endpoints,
+                kline_type,
+                baseline_offset,
+            };
+            provider.fetch(codes, api_type).await
+        }
+    }
+}
+
+// tick、K 线明细、昨收与推送等附加数据目前只有 Alltick 提供，不经过 QuoteProvider；
+// 模拟数据模式下不可用，跳过这些请求，保证不发起任何网络请求。
+fn alltick_extras_enabled(settings: &QuoteSettings) -> bool {
+    !settings.mock_mode && settings.provider == Provider::Alltick
+}
+
+// 接口拒绝过大批次后记住的可用批次大小；缩小后每隔 BATCH_PROBE_SECONDS 尝试放大一倍，
+// 放大到覆盖全部品类时恢复为一次请求。
+#[derive(Default)]
+struct BatchLimit {
+    size: Option<usize>,
+    probe_at: Option<Instant>,
+}
+
+impl BatchLimit {
+    // 本次请求使用的批次大小，到达探测时间时先尝试更大的批次。
+    fn next_size(&mut self, total: usize) -> usize {
+        if let (Some(size), Some(probe_at)) = (self.size, self.probe_at) {
+            if Instant::now() >= probe_at {
+                let larger = size.saturating_mul(2);
+                log_line(&format!("[xau-tray] probe larger batch: {larger} codes"));
+                if larger >= total {
+                    *self = Self::default();
+                } else {
+                    self.size = Some(larger);
+                    self.probe_at = Some(Instant::now() + Duration::from_secs(BATCH_PROBE_SECONDS));
+                }
+            }
+        }
+        self.size.unwrap_or(total).max(1)
+    }
+
+    // 接口拒绝了 rejected 个代码的批次：减半并推迟下一次探测，返回新的批次大小。
+    fn shrink(&mut self, rejected: usize) -> usize {
+        let smaller = rejected.div_ceil(2);
+        self.size = Some(self.size.map_or(smaller, |size| size.min(smaller)));
+        self.probe_at = Some(Instant::now() + Duration::from_secs(BATCH_PROBE_SECONDS));
+        smaller
+    }
+}
+
+// 按批次大小分批请求并合并结果；接口以代码过多拒绝某一批时只将该批减半后重试，
+// 已成功的批次不再重复请求。ret 取第一个非 200 的批次，条数与耗时累加。
+async fn fetch_batch_quotes_adaptive(
+    token: &str,
+    codes: &[String],
+    settings: &QuoteSettings,
+    limit: &mut BatchLimit,
+) -> Result<(HashMap<String, Quote>, FetchMeta), FetchError> {
+    let size = limit.next_size(codes.len());
+    let mut pending: VecDeque<&[String]> = codes.chunks(size).collect();
+    let mut map = HashMap::new();
+    let mut meta = FetchMeta {
+        ret: 200,
+        items: 0,
+        elapsed_ms: 0,
+    };
+    while let Some(chunk) = pending.pop_front() {
+        match fetch_batch_quotes(
+            token,
+            chunk,
+            settings,
+            KLINE_MINUTE,
+            settings.baseline_offset,
+        )
+        .await
+        {
+            Ok((chunk_map, chunk_meta)) => {
+                map.extend(chunk_map);
+                if meta.ret == 200 {
+                    meta.ret = chunk_meta.ret;
+                }
+                meta.items += chunk_meta.items;
+                meta.elapsed_ms += chunk_meta.elapsed_ms;
+            }
+            Err(err) if err.is_batch_too_large() && chunk.len() > 1 => {
+                let smaller = limit.shrink(chunk.len());
+                log_line(&format!(
+                    "[xau-tray] batch of {} codes rejected, retry with {smaller}",
+                    chunk.len()
+                ));
+                for part in chunk.chunks(smaller).rev() {
+                    pending.push_front(part);
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((map, meta))
+}
+
+// 模拟数据模式：按代码生成基准价，价格随时间缓慢漂移，不发起网络请求。
+fn mock_quotes(codes: &[String]) -> (HashMap<String, Quote>, FetchMeta) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut map = HashMap::new();
+    for code in codes {
+        let seed = code
+            .bytes()
+            .fold(0u64, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u64));
+        let open = 10.0 + (seed % 5000) as f64;
+        let phase = (seed % 360) as f64;
+        let drift = ((now as f64 / 60.0) + phase).sin() * 0.01;
+        map.insert(
+            code.clone(),
+            Quote {
+                price: open * (1.0 + drift),
+                timestamp: now,
+                open,
+                volume: Some((seed % 1000) as f64 + (now % 60) as f64),
+            },
+        );
+    }
+    let meta = FetchMeta {
+        ret: 200,
+        items: map.len(),
+        elapsed_ms: 0,
+    };
+    (map, meta)
+}
+
+// 拉取单个品类最近 count 根 K 线，按时间升序返回；无法解析的 K 线会被跳过。
+async fn fetch_candles(
+    token: &str,
+    code: &str,
+    api_type: ApiType,
+    use_system_proxy: bool,
+    endpoints: &[String],
+    kline_type: u8,
+    count: u32,
+) -> Result<Vec<Candle>, FetchError> {
+    let codes = [code.to_string()];
+    let payload = fetch_kline_payload(
+        token,
+        &codes,
+        api_type,
+        use_system_proxy,
+        endpoints,
+        kline_type,
+        count,
+    )
+    .await?;
+    let mut candles: Vec<Candle> = payload
+        .data
+        .kline_list
+        .into_iter()
+        .filter(|item| item.code == code)
+        .flat_map(|item| item.kline_data)
+        .filter_map(|kline| {
+            let parse = |field: &str, value: &Option<String>| {
+                let value = value.as_deref().filter(|v| !v.trim().is_empty())?;
+                parse_api_number(code, field, value)
+            };
+            Some(Candle {
+                timestamp: parse_api_timestamp(code, &kline.timestamp)?,
+                high: parse("high_price", &kline.high_price)?,
+                low: parse("low_price", &kline.low_price)?,
+                close: parse_api_number(code, "close_price", &kline.close_price)?,
+                volume: parse("volume", &kline.volume).unwrap_or(0.0),
+            })
+        })
+        .collect();
+    candles.sort_by_key(|candle| candle.timestamp);
+    Ok(candles)
+}
+
+// 最近 period 根 K 线收盘价的简单均值；K 线不足时返回 None。
+fn simple_moving_average(candles: &[Candle], period: u32) -> Option<f64> {
+    let period = period as usize;
+    if period == 0 || candles.len() < period {
+        return None;
+    }
+    let recent = &candles[candles.len() - period..];
+    Some(recent.iter().map(|candle| candle.close).sum::<f64>() / period as f64)
+}
+
+// 日内 VWAP 累计状态：缓存已收盘 K 线的 Σ(典型价×量) 与 Σ量，
+// 按 K 线时间戳的 UTC 日期判断开盘，跨日时清零。
+#[derive(Default)]
+struct VwapState {
+    day: u64,
+    last_closed_ts: u64,
+    sum_price_volume: f64,
+    sum_volume: f64,
+    value: Option<f64>,
+    updated_at: Option<Instant>,
+}
+
+impl VwapState {
+    // 距上次拉取超过间隔时才需要重新请求。
+    fn refresh_due(&self) -> bool {
+        self.updated_at
+            .is_none_or(|at| at.elapsed() >= Duration::from_secs(VWAP_REFRESH_SECONDS))
+    }
+
+    // 累加新收盘的 K 线；最后一根仍在形成中，只参与本次结果、不计入累计。
+    fn update(&mut self, candles: &[Candle]) {
+        self.updated_at = Some(Instant::now());
+        let Some((forming, closed)) = candles.split_last() else {
+            return;
+        };
+        let day = forming.timestamp / 86_400;
+        if day != self.day {
+            *self = Self {
+                day,
+                updated_at: self.updated_at,
+                ..Default::default()
+            };
+        }
+        let typical = |candle: &Candle| (candle.high + candle.low + candle.close) / 3.0;
+        for candle in closed {
+            if candle.timestamp / 86_400 == day && candle.timestamp > self.last_closed_ts {
+                self.sum_price_volume += typical(candle) * candle.volume;
+                self.sum_volume += candle.volume;
+                self.last_closed_ts = candle.timestamp;
+            }
+        }
+        let total_volume = self.sum_volume + forming.volume;
+        self.value = if total_volume > 0.0 {
+            Some((self.sum_price_volume + typical(forming) * forming.volume) / total_volume)
+        } else {
+            None
+        };
+    }
+}
+
+// 拉取各品类最近两根日线，返回 {code -> (上一交易日收盘价, 最新日线时间戳)}。
+// 日线只存在于交易日，因此周末、节假日后取到的即为最近一个交易日的收盘价。
+async fn fetch_previous_closes(
+    token: &str,
+    codes: &[String],
+    api_type: ApiType,
+    use_system_proxy: bool,
+    endpoints: &[String],
+) -> Result<HashMap<String, (f64, u64)>, FetchError> {
+    let payload = fetch_kline_payload(
+        token,
+        codes,
+        api_type,
+        use_system_proxy,
+        endpoints,
+        ChangeAnchor::Day.kline_type(),
+        2,
+    )
+    .await?;
+    let mut map = HashMap::new();
+    for item in payload.data.kline_list {
+        let mut klines: Vec<(u64, f64)> = item
+            .kline_data
+            .iter()
+            .filter_map(|kline| {
+                Some((
+                    parse_api_timestamp(&item.code, &kline.timestamp)?,
+                    parse_api_number(&item.code, "close_price", &kline.close_price)?,
+                ))
+            })
+            .collect();
+        klines.sort_by_key(|(ts, _)| *ts);
+        if let [.., (_, prev_close), (latest_ts, _)] = klines.as_slice() {
+            map.insert(item.code, (*prev_close, *latest_ts));
+        }
+    }
+    Ok(map)
+}
+
+// 错误类别：网络/请求层错误、接口返回的业务错误（ret != 200），或响应无法按预期结构解析。
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum FetchErrorKind {
+    Network,
+    Business,
+    Decode,
+}
+
+// 用于在 tooltip 中展示错误细节与接口 msg。
+#[derive(Serialize, Clone, Debug)]
+struct FetchError {
+    kind: FetchErrorKind,
+    detail: String,
+    msg: Option<String>,
+    // 业务错误时接口返回的 ret。
+    ret: Option<i64>,
+}
+
+impl FetchError {
+    fn new(detail: String) -> Self {
+        Self {
+            kind: FetchErrorKind::Network,
+            detail,
+            msg: None,
+            ret: None,
+        }
+    }
+
+    // 接口业务错误，携带接口返回的 ret 与 msg。
+    fn with_msg(ret: i64, msg: Option<String>) -> Self {
+        Self {
+            kind: FetchErrorKind::Business,
+            detail: format!("api ret={ret}"),
+            msg,
+            ret: Some(ret),
+        }
+    }
+
+    // 响应解析失败（如接口结构变化），附带响应体开头部分便于排查实际返回内容。
+    fn decode(err: &serde_json::Error, body_text: &str) -> Self {
+        let mut preview: String = body_text.chars().take(DECODE_BODY_PREVIEW_CHARS).collect();
+        if preview.len() < body_text.len() {
+            preview.push('…');
+        }
+        Self {
+            kind: FetchErrorKind::Decode,
+            detail: format!("decode error: {err} body={preview}"),
+            msg: None,
+            ret: None,
+        }
+    }
+
+    // 接口是否因单次请求的代码数量超过上限而拒绝。
+    fn is_batch_too_large(&self) -> bool {
+        self.ret == Some(API_RET_TOO_MANY_CODES)
     }
 
     // 将错误结构转换为 tooltip 文本。
-    fn tooltip_lines(&self) -> Vec<String> {
-        let mut lines = vec![format!("错误: {}", self.detail)];
+    fn tooltip_lines(&self, locale: &str) -> Vec<String> {
+        let mut lines = vec![format!("{}: {}", tr(locale, "error"), self.detail)];
         if let Some(msg) = self.msg.as_ref() {
             let msg = msg.trim();
             if !msg.is_empty() {
@@ -415,13 +2645,114 @@ impl FetchError {
     }
 }
 
-// 错误时的状态栏标题，使用红点提示。
-fn error_title(base: &str) -> String {
+// 判断锚点价格是否需要重新拉取：锚点类型变化、缺少品类或已进入新周期。
+fn anchor_refresh_due(
+    anchor: ChangeAnchor,
+    cached_anchor: Option<ChangeAnchor>,
+    anchor_prices: &HashMap<String, (f64, u64)>,
+    codes: &[String],
+) -> bool {
+    if cached_anchor != Some(anchor) {
+        return true;
+    }
+    let current = anchor.period_key(&chrono::Local::now());
+    codes.iter().any(|code| match anchor_prices.get(code) {
+        Some((_, ts)) => chrono::DateTime::from_timestamp(*ts as i64, 0)
+            .map(|time| anchor.period_key(&time.with_timezone(&chrono::Local)) != current)
+            .unwrap_or(true),
+        None => true,
+    })
+}
+
+// 格式化锚点涨跌幅行，如 "  较周开 +1.23% (2300.00)"。
+fn format_anchor_line(
+    anchor: ChangeAnchor,
+    price: Option<f64>,
+    anchor_price: Option<f64>,
+    locale: &str,
+) -> String {
+    let label = anchor.label(locale);
+    match (price, anchor_price) {
+        (Some(price), Some(base)) if base != 0.0 => {
+            let change = (price - base) / base * 100.0;
+            format!("  {label} {change:+.2}% ({base:.2})")
+        }
+        _ => format!("  {label} --"),
+    }
+}
+
+// 格式化昨收对比行，如 "  昨收 2330.00 (+0.66%)"。
+fn format_prev_close_line(
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    price: Option<f64>,
+    prev_close: Option<f64>,
+    locale: &str,
+) -> String {
+    let label = tr(locale, "prev_close");
+    let shown = price.and_then(|price| display_value(symbol, price));
+    let shown_base = prev_close.and_then(|base| display_value(symbol, base));
+    match (shown, shown_base, prev_close) {
+        (Some(price), Some(base), Some(prev_close)) if base != 0.0 => {
+            let change = change_percent(base, price);
+            let base_text = format_price(settings, symbol, prev_close);
+            format!("  {label} {base_text} ({change:+.2}%)")
+        }
+        _ => format!("  {label} --"),
+    }
+}
+
+// 格式化入场价对比行，如 "  (+1.20% vs 入场 2300.00)"。
+fn format_entry_line(
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    price: Option<f64>,
+    entry: f64,
+    locale: &str,
+) -> String {
+    let label = tr(locale, "entry");
+    let entry_text = format_price(settings, symbol, entry);
+    let shown = price.and_then(|price| display_value(symbol, price));
+    match (shown, display_value(symbol, entry)) {
+        (Some(price), Some(entry)) => {
+            let change = change_percent(entry, price);
+            format!("  ({change:+.2}% vs {label} {entry_text})")
+        }
+        _ => format!("  (-- vs {label} {entry_text})"),
+    }
+}
+
+// 将较大的数值压缩为 k/M/B 形式，如 1234567 -> "1.23M"。
+fn format_compact(value: f64) -> String {
+    let abs = value.abs();
+    if abs >= 1_000_000_000.0 {
+        format!("{:.2}B", value / 1_000_000_000.0)
+    } else if abs >= 1_000_000.0 {
+        format!("{:.2}M", value / 1_000_000.0)
+    } else if abs >= 1_000.0 {
+        format!("{:.2}k", value / 1_000.0)
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+// 提示符最多保留的字符数，避免占用过多菜单栏宽度。
+const INDICATOR_MAX_CHARS: usize = 4;
+
+// 规范化提示符：去除首尾空白并截断到最大长度。
+fn normalize_indicator(value: &str) -> String {
+    value.trim().chars().take(INDICATOR_MAX_CHARS).collect()
+}
+
+// 错误时的状态栏标题，使用错误提示符（默认红点）；提示符为空时保持原标题。
+fn error_title(indicator: &str, base: &str) -> String {
     let title = base.trim();
-    if title.is_empty() {
-        "🔴".to_string()
+    if indicator.is_empty() {
+        title.to_string()
+    } else if title.is_empty() {
+        indicator.to_string()
     } else {
-        format!("🔴 {title}")
+        format!("{indicator} {title}")
     }
 }
 
@@ -433,9 +2764,64 @@ struct ProxySetting {
     no_proxy: Option<String>,
 }
 
+// 当前生效的代理决策，供设置界面展示。
+#[derive(Serialize, Clone)]
+struct ProxyInfo {
+    enabled: bool,
+    source: String,
+    url: Option<String>,
+    no_proxy: Option<String>,
+}
+
+// 隐藏代理 URL 中的用户名与密码，避免泄露凭据。
+fn redact_proxy_url(url: &str) -> String {
+    if let Ok(mut parsed) = reqwest::Url::parse(url) {
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            let _ = parsed.set_username("***");
+            let _ = parsed.set_password(None);
+        }
+        return parsed.to_string();
+    }
+    match url.rsplit_once('@') {
+        Some((_, host)) => format!("***@{host}"),
+        None => url.to_string(),
+    }
+}
+
+// 根据配置计算代理决策（与实际请求使用同一套逻辑）。
+fn proxy_info(use_system_proxy: bool) -> ProxyInfo {
+    let proxy_setting = if use_system_proxy {
+        system_proxy_setting()
+    } else {
+        None
+    };
+    match proxy_setting {
+        Some(proxy_setting) => {
+            let no_proxy = proxy_setting.no_proxy.clone().or_else(|| {
+                ["NO_PROXY", "no_proxy"]
+                    .iter()
+                    .find_map(|key| std::env::var(key).ok())
+                    .filter(|value| !value.trim().is_empty())
+            });
+            ProxyInfo {
+                enabled: true,
+                source: proxy_setting.source.to_string(),
+                url: Some(redact_proxy_url(&proxy_setting.url)),
+                no_proxy,
+            }
+        }
+        None => ProxyInfo {
+            enabled: false,
+            source: "direct".to_string(),
+            url: None,
+            no_proxy: None,
+        },
+    }
+}
+
 // 构建带代理/直连的 HTTP 客户端。
 fn build_http_client(proxy_setting: Option<&ProxySetting>) -> Result<reqwest::Client, String> {
-    let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(10));
+    let mut builder = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
     if let Some(proxy_setting) = proxy_setting {
         let mut proxy = reqwest::Proxy::all(proxy_setting.url.clone()).map_err(|e| e.to_string())?;
         let no_proxy = proxy_setting
@@ -482,13 +2868,25 @@ fn env_proxy_setting() -> Option<String> {
         if let Ok(value) = std::env::var(key) {
             let trimmed = value.trim();
             if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
+                return Some(normalize_proxy_url(trimmed));
             }
         }
     }
     None
 }
 
+// 代理地址缺少协议（如 "127.0.0.1:7890"）时默认补上 http://。
+fn normalize_proxy_url(value: &str) -> String {
+    if value.contains("://") {
+        value.to_string()
+    } else {
+        log_line(&format!(
+            "[xau-tray] proxy url has no scheme, assuming http://{value}"
+        ));
+        format!("http://{value}")
+    }
+}
+
 // macOS 下通过 scutil 获取系统代理与排除列表。
 #[cfg(target_os = "macos")]
 fn macos_system_proxy_url() -> Option<(String, Option<String>)> {
@@ -508,7 +2906,7 @@ fn macos_system_proxy_url() -> Option<(String, Option<String>)> {
 }
 
 // 解析 scutil 输出，优先 HTTPS/HTTP/SOCKS。
-fn parse_scutil_proxy(text: &str) -> Option<String> {
+pub(crate) fn parse_scutil_proxy(text: &str) -> Option<String> {
     scutil_proxy_url(text, "HTTPSEnable", "HTTPSProxy", "HTTPSPort", "http")
         .or_else(|| scutil_proxy_url(text, "HTTPEnable", "HTTPProxy", "HTTPPort", "http"))
         .or_else(|| scutil_proxy_url(text, "SOCKSEnable", "SOCKSProxy", "SOCKSPort", "socks5"))
@@ -537,26 +2935,24 @@ fn scutil_proxy_url(
     Some(format!("{scheme}://{host}:{port}"))
 }
 
-// 获取 scutil 输出中某个 key 的 value。
-fn scutil_value(text: &str, key: &str) -> Option<String> {
-    for line in text.lines() {
-        let line = line.trim();
-        if line.starts_with(key) {
-            if let Some((_, value)) = line.split_once(':') {
-                return Some(value.trim().to_string());
-            }
-        }
-    }
-    None
+// 获取 scutil 输出中某个 key 的 value；key 必须与冒号前的内容完全一致，避免前缀误匹配。
+pub(crate) fn scutil_value(text: &str, key: &str) -> Option<String> {
+    text.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim().to_string())
 }
 
 // 解析 scutil 的 ExceptionsList，返回逗号分隔的 no_proxy。
-fn parse_scutil_no_proxy(text: &str) -> Option<String> {
+pub(crate) fn parse_scutil_no_proxy(text: &str) -> Option<String> {
     let mut values: Vec<String> = Vec::new();
     let mut in_list = false;
     for line in text.lines() {
         let line = line.trim();
-        if line.starts_with("ExceptionsList") {
+        if line
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim() == "ExceptionsList")
+        {
             in_list = true;
             continue;
         }
@@ -591,12 +2987,12 @@ fn log_proxy_decision(proxy_setting: Option<&ProxySetting>) {
     }
 }
 
-// 执行实际 HTTP 请求并解析响应。
-async fn send_batch_request(
+// 执行 HTTP 请求，返回状态码与原始响应文本。
+async fn send_raw_request(
     proxy_setting: Option<&ProxySetting>,
     url: reqwest::Url,
     body: &serde_json::Value,
-) -> Result<BatchResp, String> {
+) -> Result<(reqwest::StatusCode, String), String> {
     let client = build_http_client(proxy_setting)?;
     let resp = client
         .post(url)
@@ -609,13 +3005,39 @@ async fn send_batch_request(
         .text()
         .await
         .map_err(|e| format_reqwest_error(&e))?;
+    Ok((status, body_text))
+}
+
+// 执行实际 HTTP 请求并解析响应。
+async fn send_batch_request(
+    proxy_setting: Option<&ProxySetting>,
+    url: reqwest::Url,
+    body: &serde_json::Value,
+) -> Result<BatchResp, FetchError> {
+    let (status, body_text) = send_raw_request(proxy_setting, url, body)
+        .await
+        .map_err(FetchError::new)?;
     if !status.is_success() {
-        return Err(format!("http status {status} body={body_text}"));
+        return Err(FetchError::new(format!(
+            "http status {status} body={body_text}"
+        )));
     }
-    serde_json::from_str::<BatchResp>(&body_text).map_err(|e| e.to_string())
+    serde_json::from_str::<BatchResp>(&body_text).map_err(|e| FetchError::decode(&e, &body_text))
+}
+
+// 根据 format_reqwest_error 标注的分类判断是否为连接失败或超时，只有这类错误才切换备用地址。
+fn is_connection_failure(detail: &str) -> bool {
+    let Some(start) = detail.rfind(" (") else {
+        return false;
+    };
+    detail[start..]
+        .trim_start_matches(" (")
+        .split([',', ')'])
+        .any(|tag| matches!(tag.trim(), "connect" | "timeout"))
 }
 
 // 将 reqwest 错误展开为更可读的文本（含分类与原因链）。
+// 错误文本中的请求 URL 带有 token，这里统一脱敏，避免写入日志文件或返回给前端。
 fn format_reqwest_error(err: &reqwest::Error) -> String {
     let mut details = err.to_string();
     let mut tags: Vec<String> = Vec::new();
@@ -650,6 +3072,9 @@ fn format_reqwest_error(err: &reqwest::Error) -> String {
     if !causes.is_empty() {
         details = format!("{details}; causes: {}", causes.join(" | "));
     }
+    if let Some(url) = err.url() {
+        details = details.replace(url.as_str(), &redact_token_url(url));
+    }
 
     details
 }
@@ -657,46 +3082,742 @@ fn format_reqwest_error(err: &reqwest::Error) -> String {
 // Tauri 命令：获取当前设置。
 #[tauri::command]
 fn get_settings(state: tauri::State<'_, AppState>) -> QuoteSettings {
-    state.settings.lock().unwrap().clone()
+    lock_or_recover(&state.settings).clone()
 }
 
-// Tauri 命令：保存设置并更新内存状态。
+// Tauri 命令：返回配置文件的完整路径，便于前端打开配置目录或排查问题。
 #[tauri::command]
-fn save_settings_command(
-    app: tauri::AppHandle,
-    state: tauri::State<'_, AppState>,
-    settings: QuoteSettings,
-) -> Result<QuoteSettings, String> {
+fn settings_path(app: tauri::AppHandle) -> Result<String, String> {
+    settings_file_path(&app).map(|path| path.to_string_lossy().to_string())
+}
+
+// Tauri 命令：返回日志文件末尾最多 max_lines 行；只从文件末尾读取有限字节，避免加载大文件。
+#[tauri::command]
+fn read_logs(state: tauri::State<'_, AppState>, max_lines: usize) -> Result<Vec<String>, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let Some(path) = LOG_FILE_PATH.get() else {
+        let locale = lock_or_recover(&state.settings).locale.clone();
+        return Err(tr(effective_locale(&locale), "err_log_uninitialized").to_string());
+    };
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.to_string()),
+    };
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    let start = len.saturating_sub(READ_LOGS_MAX_BYTES);
+    file.seek(SeekFrom::Start(start))
+        .map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    let text = String::from_utf8_lossy(&bytes);
+    let mut lines: Vec<&str> = text.lines().collect();
+    // 从中间开始读取时，第一行可能不完整，丢弃。
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+    let keep = max_lines.min(READ_LOGS_MAX_LINES);
+    let skip = lines.len().saturating_sub(keep);
+    Ok(lines[skip..].iter().map(|line| line.to_string()).collect())
+}
+
+// Tauri 命令：返回托盘图标创建失败的本地化提示，创建成功时为 None。
+#[tauri::command]
+fn get_tray_error(state: tauri::State<'_, AppState>) -> Option<String> {
+    let err = lock_or_recover(&state.tray_error).clone()?;
+    let locale = lock_or_recover(&state.settings).locale.clone();
+    Some(tr(effective_locale(&locale), "tray_error").replace("{err}", &err))
+}
+
+// Tauri 命令：返回旧版 token 文件路径，用于迁移问题诊断。
+#[tauri::command]
+fn legacy_token_path(app: tauri::AppHandle) -> Result<String, String> {
+    legacy_token_file_path(&app).map(|path| path.to_string_lossy().to_string())
+}
+
+// Tauri 命令：返回支持的 K 线类型代码与名称。
+#[tauri::command]
+fn kline_types() -> Vec<(i64, String)> {
+    KLINE_TYPES
+        .iter()
+        .map(|(code, label)| (i64::from(*code), label.to_string()))
+        .collect()
+}
+
+// Tauri 命令：一次性返回设置、实时行情、轮询状态与代理信息，减少设置窗口初始化时的调用次数。
+#[tauri::command]
+fn get_app_snapshot(state: tauri::State<'_, AppState>) -> AppSnapshot {
+    let settings = lock_or_recover(&state.settings).clone();
+    let proxy = proxy_info(settings.use_system_proxy);
+    let metrics = lock_or_recover(&state.metrics);
+    AppSnapshot {
+        settings,
+        prices: metrics.last_prices.clone(),
+        trends: metrics.trends.clone(),
+        status: PollStatus {
+            last_refresh_at: metrics.last_refresh_at.clone(),
+            last_error: metrics.last_error.clone(),
+            consecutive_successes: metrics.consecutive_successes,
+            consecutive_failures: metrics.consecutive_failures,
+            backoff_seconds: metrics.backoff_seconds,
+        },
+        proxy,
+    }
+}
+
+// Tauri 命令：返回启动（或上次重置）以来各品类的首个价格、最高、最低、当前价格与涨跌幅。
+#[tauri::command]
+fn session_stats(state: tauri::State<'_, AppState>) -> SessionStats {
+    let symbols = lock_or_recover(&state.settings).symbols.clone();
+    let metrics = lock_or_recover(&state.metrics);
+    let stats = symbols
+        .iter()
+        .filter_map(|symbol| {
+            let (open, high, low) = *metrics.session_ranges.get(&symbol.code)?;
+            let current = *metrics.last_prices.get(&symbol.code)?;
+            Some(SymbolSessionStats {
+                code: symbol.code.clone(),
+                open,
+                high,
+                low,
+                current,
+                change_percent: change_percent(open, current),
+            })
+        })
+        .collect();
+    SessionStats {
+        started_at: metrics.session_started_at.clone(),
+        symbols: stats,
+    }
+}
+
+// Tauri 命令：清空会话统计，下一次刷新起重新开始计算。
+#[tauri::command]
+fn reset_session_stats(state: tauri::State<'_, AppState>) {
+    let mut metrics = lock_or_recover(&state.metrics);
+    metrics.session_ranges.clear();
+    metrics.session_started_at = None;
+}
+
+// CSV 字段转义：包含逗号、引号或换行时用双引号包裹。
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Tauri 命令：将当前各品类的价格与趋势追加到应用数据目录下的 CSV，
+// 每个品类一行（时间, 代码, 价格, 趋势），品类增减不影响已有数据的列含义。
+#[tauri::command]
+fn append_snapshot_csv(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    use std::io::Write;
+
+    let symbols = lock_or_recover(&state.settings).symbols.clone();
+    let timestamp = chrono::Local::now().to_rfc3339();
+    let mut rows = String::new();
+    {
+        let metrics = lock_or_recover(&state.metrics);
+        for symbol in &symbols {
+            let price = metrics
+                .last_prices
+                .get(&symbol.code)
+                .map(|price| format!("{price:.2}"))
+                .unwrap_or_default();
+            let trend = metrics
+                .trends
+                .get(&symbol.code)
+                .cloned()
+                .unwrap_or_default();
+            rows.push_str(&format!(
+                "{timestamp},{},{price},{}\n",
+                csv_field(&symbol.code),
+                csv_field(&trend)
+            ));
+        }
+    }
+
+    let base = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+    let path = base.join(SNAPSHOT_CSV_FILE);
+    let is_new = !path.exists();
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    if is_new {
+        rows.insert_str(0, "timestamp,code,price,trend\n");
+    }
+    file.write_all(rows.as_bytes()).map_err(|e| e.to_string())
+}
+
+// 规范化并保存设置，使其生效并唤醒轮询任务，用于只修改个别字段的快捷命令。
+fn store_settings(app: &AppHandle, next: QuoteSettings) -> Result<QuoteSettings, String> {
+    let normalized = normalize_settings(next);
+    save_settings(app, &normalized)?;
+    apply_settings(app, normalized.clone());
+    app.state::<AppState>().shutdown.wake();
+    Ok(normalized)
+}
+
+// Tauri 命令：固定展示指定品类，一次设置固定模式与 fixed_symbol 并保存。
+#[tauri::command]
+fn pin_symbol(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    code: String,
+) -> Result<QuoteSettings, String> {
+    let code = code.trim().to_string();
+    let mut next = lock_or_recover(&state.settings).clone();
+    // 与 normalize_settings 的校验一致：代码须在品类列表中（或为已配置的组合指数）。
+    let exists = next.symbols.iter().any(|s| s.code == code)
+        || (code == PORTFOLIO_CODE && !next.portfolio.is_empty());
+    if !exists {
+        let locale = effective_locale(&next.locale);
+        return Err(tr(locale, "err_symbol_not_found").replace("{code}", &code));
+    }
+    next.display_mode = DisplayMode::Fixed;
+    next.fixed_symbol = Some(code);
+    store_settings(&app, next)
+}
+
+// Tauri 命令：将单个设置字段恢复为默认值并保存；品类列表按当前接口类型回填默认品类。
+#[tauri::command]
+fn reset_field(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    field: String,
+) -> Result<QuoteSettings, String> {
+    let current = lock_or_recover(&state.settings).clone();
+    let mut value = serde_json::to_value(&current).map_err(|e| e.to_string())?;
+    let defaults = serde_json::to_value(QuoteSettings::default()).map_err(|e| e.to_string())?;
+    let Some(default) = defaults.get(&field) else {
+        let locale = effective_locale(&current.locale);
+        return Err(tr(locale, "err_unknown_field").replace("{field}", &field));
+    };
+    // 品类按当前接口类型恢复默认列表，不受是否允许空列表影响。
+    let default = if field == "symbols" {
+        serde_json::to_value(default_symbols_for(current.api_type)).map_err(|e| e.to_string())?
+    } else {
+        default.clone()
+    };
+    value[&field] = default;
+    let next: QuoteSettings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    store_settings(&app, next)
+}
+
+// Tauri 命令：取消固定展示，恢复轮播模式。
+#[tauri::command]
+fn unpin(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<QuoteSettings, String> {
+    let mut next = lock_or_recover(&state.settings).clone();
+    next.display_mode = DisplayMode::Rotate;
+    store_settings(&app, next)
+}
+
+// Tauri 命令：临时锁定托盘展示的品类（None 解除），覆盖轮播但不修改持久化的展示模式，重启后失效。
+#[tauri::command]
+fn lock_display(state: tauri::State<'_, AppState>, code: Option<String>) {
+    let code = code
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty());
+    *lock_or_recover(&state.display_lock) = code;
+}
+
+// Tauri 命令：临时加速刷新，在 duration_secs 秒内按 seconds 秒间隔刷新，到期后恢复配置的间隔；
+// duration_secs 为 0 时立即结束加速。不写入设置文件，重启后失效。
+#[tauri::command]
+fn turbo_mode(
+    state: tauri::State<'_, AppState>,
+    seconds: u64,
+    duration_secs: u64,
+) -> Result<(), String> {
+    let locale = lock_or_recover(&state.settings).locale.clone();
+    let locale = effective_locale(&locale);
+    let mut turbo = lock_or_recover(&state.turbo);
+    if duration_secs == 0 {
+        *turbo = None;
+        log_line("[xau-tray] turbo mode cancelled");
+    } else {
+        if duration_secs > TURBO_MAX_DURATION_SECONDS {
+            return Err(tr(locale, "err_turbo_duration")
+                .replace("{max}", &TURBO_MAX_DURATION_SECONDS.to_string()));
+        }
+        let seconds = seconds.max(TURBO_MIN_SECONDS);
+        *turbo = Some((Instant::now() + Duration::from_secs(duration_secs), seconds));
+        log_line(&format!(
+            "[xau-tray] turbo mode: every {seconds}s for {duration_secs}s"
+        ));
+    }
+    drop(turbo);
+    // 唤醒轮询任务，立即按新的间隔刷新。
+    state.shutdown.wake();
+    Ok(())
+}
+
+// 请求轮询任务立即刷新：置位后唤醒睡眠中的轮询任务。
+fn request_refresh(state: &AppState) {
+    state.refresh_requested.store(true, Ordering::SeqCst);
+    state.shutdown.wake();
+}
+
+// Tauri 命令：立即刷新行情，不等待刷新间隔；错误退避期间同样立即重试。
+#[tauri::command]
+fn refresh_now(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    log_line("[xau-tray] refresh requested");
+    request_refresh(&state);
+    Ok(())
+}
+
+// Tauri 命令：获取当前生效的代理决策（URL 已脱敏）。
+#[tauri::command]
+fn get_proxy_info(state: tauri::State<'_, AppState>) -> ProxyInfo {
+    let use_system_proxy = lock_or_recover(&state.settings).use_system_proxy;
+    proxy_info(use_system_proxy)
+}
+
+// 将新设置写入内存状态并使其生效：接口类型或 token 变化时重启轮询任务，并重建托盘菜单。
+// 保存与重新读取共用，返回替换前的设置。
+fn apply_settings(app: &AppHandle, settings: QuoteSettings) -> QuoteSettings {
+    let state = app.state::<AppState>();
+    let previous = std::mem::replace(&mut *lock_or_recover(&state.settings), settings.clone());
+    if polling_restart_required(&previous, &settings) {
+        restart_polling(app);
+    }
+    refresh_tray_menu(app);
+    previous
+}
+
+// 保存设置的结果：规范化后的设置，以及是否需要重启应用才能生效。
+#[derive(Serialize)]
+struct SaveSettingsResult {
+    settings: QuoteSettings,
+    requires_restart: bool,
+}
+
+// Tauri 命令：保存设置并更新内存状态。
+#[tauri::command]
+fn save_settings_command(
+    app: tauri::AppHandle,
+    settings: QuoteSettings,
+) -> Result<SaveSettingsResult, String> {
     let normalized = normalize_settings(settings);
     save_settings(&app, &normalized)?;
-    *state.settings.lock().unwrap() = normalized.clone();
-    Ok(normalized)
+    let previous = apply_settings(&app, normalized.clone());
+    // 非阻断提示通过事件发送给前端，不影响保存结果。
+    let locale = effective_locale(&normalized.locale);
+    let mut warnings: Vec<String> = rotation_warning(&normalized, locale).into_iter().collect();
+    if stream_blocked_by_proxy(&normalized) {
+        warnings.push(tr(locale, "stream_proxy_unsupported").to_string());
+    }
+    if !warnings.is_empty() {
+        let _ = app.emit(
+            "settings-warning",
+            AlertPayload {
+                message: warnings.join("\n"),
+                error: None,
+            },
+        );
+    }
+    Ok(SaveSettingsResult {
+        requires_restart: settings_requires_restart(&previous, &normalized),
+        settings: normalized,
+    })
+}
+
+// Tauri 命令：从磁盘重新读取设置（用于手动编辑配置文件后），与保存设置相同立即生效。
+#[tauri::command]
+fn reload_settings(app: tauri::AppHandle) -> QuoteSettings {
+    let settings = load_settings(&app);
+    apply_settings(&app, settings.clone());
+    settings
 }
 
-// 格式化 tooltip 行，包含趋势、名称与价格。
-fn format_price_line(symbol: &SymbolItem, price: Option<f64>, trend: Option<&str>) -> String {
-    let name = if symbol.label.is_empty() {
-        symbol.code.as_str()
+// 去掉代码最后一个 "." 之后的市场后缀，如 ".IXIC.US" -> ".IXIC"；没有后缀时原样返回。
+fn strip_market_suffix(code: &str) -> &str {
+    match code.rsplit_once('.') {
+        Some((symbol, market))
+            if !symbol.is_empty()
+                && !market.is_empty()
+                && market.chars().all(|c| c.is_ascii_alphabetic()) =>
+        {
+            symbol
+        }
+        _ => code,
+    }
+}
+
+// 按模板生成品类显示名称，支持 {label} 与 {code} 占位符。
+// strip_suffix 为 true 且未设置自定义名称时，名称中去掉市场后缀（如 "000001.SH" -> "000001"）。
+fn symbol_display_name(symbol: &SymbolItem, template: &str, strip_suffix: bool) -> String {
+    let label = if symbol.label.is_empty() || symbol.label == symbol.code {
+        if strip_suffix {
+            strip_market_suffix(&symbol.code)
+        } else {
+            symbol.code.as_str()
+        }
     } else {
         symbol.label.as_str()
     };
+    template
+        .replace("{code}", &symbol.code)
+        .replace("{label}", label)
+}
+
+// Tauri 命令：按当前设置重新发起一次批量请求，返回原始响应文本用于排查问题（token 已脱敏）。
+// 与轮询相同，主地址连接失败或超时时依次尝试备用地址。
+#[tauri::command]
+async fn debug_raw_response(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    let settings = lock_or_recover(&state.settings).clone();
+    let locale = effective_locale(&settings.locale);
+    if !alltick_extras_enabled(&settings) {
+        return Err(tr(locale, "err_mock_unavailable").to_string());
+    }
+    let tokens = parse_tokens(&settings.token);
+    let token = tokens
+        .first()
+        .ok_or_else(|| tr(locale, "err_token_empty").to_string())?;
+    let codes = request_codes(&settings.symbols);
+    let proxy_setting = request_proxy_setting(settings.use_system_proxy);
+    let base_urls = endpoint_candidates(&settings.endpoints);
+    let mut attempt = 0;
+    loop {
+        let base_url = base_urls[attempt];
+        let (url, body) =
+            build_batch_request(base_url, token, &codes, settings.api_type, KLINE_MINUTE, 1)
+                .map_err(|e| e.detail)?;
+        let redacted_url = redact_token_url(&url);
+        match send_raw_request(proxy_setting.as_ref(), url, &body).await {
+            Ok((status, body_text)) => {
+                return Ok(format!(
+                    "POST {redacted_url}\nstatus: {status}\n\n{body_text}"
+                ));
+            }
+            Err(err) => {
+                attempt += 1;
+                if !is_connection_failure(&err) || attempt >= base_urls.len() {
+                    return Err(err);
+                }
+                log_line(&format!(
+                    "[xau-tray] {base_url} unreachable, fall back to {}",
+                    base_urls[attempt]
+                ));
+            }
+        }
+    }
+}
+
+// Tauri 命令：估算本地时钟与服务器的偏差（秒，正数表示本地时钟偏快）。
+// 取第一个品类最新一根 1 分钟 K 线的时间戳与本地时间比较；交易时段内正常时钟的结果在 0~60 秒之间，
+// 休市期间最新 K 线停留在收盘时刻，结果会包含休市时长，不宜用于校准。
+#[tauri::command]
+async fn server_time_skew(state: tauri::State<'_, AppState>) -> Result<i64, String> {
+    let settings = lock_or_recover(&state.settings).clone();
+    let locale = effective_locale(&settings.locale);
+    if !alltick_extras_enabled(&settings) {
+        return Err(tr(locale, "err_mock_unavailable").to_string());
+    }
+    let tokens = parse_tokens(&settings.token);
+    let token = tokens
+        .first()
+        .ok_or_else(|| tr(locale, "err_token_empty").to_string())?;
+    let codes = request_codes(&settings.symbols);
+    let code = codes
+        .first()
+        .ok_or_else(|| tr(locale, "err_no_symbols").to_string())?;
+    let candles = fetch_candles(
+        token,
+        code,
+        settings.api_type,
+        settings.use_system_proxy,
+        &settings.endpoints,
+        KLINE_MINUTE,
+        1,
+    )
+    .await
+    .map_err(|e| e.detail)?;
+    let latest = candles
+        .iter()
+        .map(|candle| candle.timestamp)
+        .max()
+        .ok_or_else(|| tr(locale, "err_no_kline_data").replace("{code}", code))?;
+    let skew = chrono::Utc::now().timestamp() - latest as i64;
+    log_line(&format!("[xau-tray] server time skew: {skew}s ({code})"));
+    Ok(skew)
+}
+
+// Tauri 命令：不带 token 请求一次行情接口主机，返回往返耗时（毫秒），
+// 用于区分"服务器不可达"与"token 无效"。收到任何 HTTP 响应都视为可达；
+// 主地址不可达时依次尝试已保存的备用地址，返回第一个可达地址的耗时。
+#[tauri::command]
+async fn ping_api(
+    state: tauri::State<'_, AppState>,
+    api_type: ApiType,
+    use_system_proxy: bool,
+) -> Result<u128, String> {
+    let endpoints = lock_or_recover(&state.settings).endpoints.clone();
+    let path = match api_type {
+        ApiType::Commodity => "/quote-b-api/",
+        ApiType::Stock => "/quote-stock-b-api/",
+    };
+    let proxy_setting = request_proxy_setting(use_system_proxy);
+    let client = build_http_client(proxy_setting.as_ref())?;
+    let mut last_error = String::new();
+    for base_url in endpoint_candidates(&endpoints) {
+        let url = format!("{base_url}{path}");
+        let started = Instant::now();
+        match client.head(&url).send().await {
+            Ok(_) => {
+                let latency = started.elapsed().as_millis();
+                log_line(&format!("[xau-tray] ping {url}: {latency}ms"));
+                return Ok(latency);
+            }
+            Err(e) => {
+                last_error = format_reqwest_error(&e);
+                log_line(&format!("[xau-tray] ping {url} failed: {last_error}"));
+            }
+        }
+    }
+    Err(last_error)
+}
+
+// Tauri 命令：按给定的接口类型与 token 查询任意代码的最新价格，用于添加品类前预览；
+// 不修改设置，也不影响轮询任务。token 为空时使用已保存的第一个 token。
+#[tauri::command]
+async fn preview_quotes(
+    app: tauri::AppHandle,
+    codes: Vec<String>,
+    api_type: ApiType,
+    token: Option<String>,
+    use_system_proxy: bool,
+) -> Result<HashMap<String, f64>, String> {
+    let codes: Vec<String> = codes
+        .iter()
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty())
+        .collect();
+    if codes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut settings = lock_or_recover(&app.state::<AppState>().settings).clone();
+    settings.api_type = api_type;
+    settings.use_system_proxy = use_system_proxy;
+    let locale = effective_locale(&settings.locale);
+    let token = token
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+        .or_else(|| parse_tokens(&settings.token).into_iter().next())
+        .ok_or_else(|| tr(locale, "err_token_empty").to_string())?;
+    let (quotes, _) = fetch_batch_quotes(&token, &codes, &settings, KLINE_MINUTE, 0)
+        .await
+        .map_err(|err| err.tooltip_lines(locale).join(" "))?;
+    Ok(quotes
+        .into_iter()
+        .map(|(code, quote)| (code, quote.price))
+        .collect())
+}
+
+// 格式化 tooltip 行，包含趋势、名称与价格；无价格时显示配置的占位符。
+fn format_price_line(
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    price: Option<f64>,
+    trend: Option<&str>,
+) -> String {
+    let name = symbol_display_name(symbol, &settings.label_template, false);
     match (trend, price) {
-        (Some(trend), Some(price)) => format!("{trend} {name} {price:.2}"),
-        _ => format!("{name} --"),
+        (Some(trend), Some(price)) => {
+            format!("{trend} {name} {}", format_price(settings, symbol, price))
+        }
+        _ => format!("{name} {}", settings.no_data_placeholder),
+    }
+}
+
+// 格式化价格数值，带上品类配置的货币符号前缀；反向报价时显示倒数，倒数无法计算时显示 "--"。
+fn format_price(settings: &QuoteSettings, symbol: &SymbolItem, price: f64) -> String {
+    let prefix = symbol.currency_symbol.as_deref().unwrap_or("");
+    let Some(price) = display_value(symbol, price) else {
+        return format!("{prefix}{}", settings.no_data_placeholder);
+    };
+    let decimals = price_decimals(symbol);
+    let price = match settings.rounding {
+        RoundingMode::HalfEven => price,
+        RoundingMode::HalfUp => round_half_up(price, decimals),
+    };
+    format!("{prefix}{price:.decimals$}")
+}
+
+// 价格显示的小数位数：反向报价的数值通常小于 1，保留 4 位，其余保留 2 位。
+fn price_decimals(symbol: &SymbolItem) -> usize {
+    if symbol.invert_price {
+        4
+    } else {
+        2
     }
 }
 
-// 格式化状态栏标题，使用名称与价格（趋势不影响标题）。
-fn format_title(symbol: &SymbolItem, price: Option<f64>, trend: Option<&str>) -> String {
-    let name = if symbol.label.is_empty() {
-        symbol.code.as_str()
+// 换算为显示用的数值：开启反向报价时取倒数，价格为 0 时无法换算返回 None。
+fn display_value(symbol: &SymbolItem, price: f64) -> Option<f64> {
+    if !symbol.invert_price {
+        Some(price)
+    } else if price != 0.0 && price.is_finite() {
+        Some(1.0 / price)
     } else {
-        symbol.label.as_str()
+        None
+    }
+}
+
+// 按四舍五入保留 decimals 位小数。先在 1e-6 精度上取整，消除二进制表示误差（如 2.675 实际为 2.67499...），
+// 再对 0.5 向远离零的方向进位。放大后超过 2^53 时 f64 已无法精确表示整数，此时跳过预取整直接进位；
+// 连 value × 10^decimals 都超过 2^53 时已没有可舍入的小数位，原样返回。
+fn round_half_up(value: f64, decimals: usize) -> f64 {
+    const EXACT_INTEGER_LIMIT: f64 = 9_007_199_254_740_992.0;
+    let factor = 10f64.powi(decimals as i32);
+    let scaled = value * factor;
+    if scaled.abs() >= EXACT_INTEGER_LIMIT {
+        return value;
+    }
+    let scaled = if scaled.abs() * 1e6 < EXACT_INTEGER_LIMIT {
+        (scaled * 1e6).round() / 1e6
+    } else {
+        scaled
     };
-    match (trend, price) {
-        (_, Some(price)) => format!("{name} {price:.2}"),
-        _ => format!("{name} --"),
+    scaled.round() / factor
+}
+
+// 格式化状态栏标题，使用名称与价格（趋势不影响标题）；无价格时显示配置的占位符。
+fn format_title(
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    price: Option<f64>,
+    trend: Option<&str>,
+) -> String {
+    let name = symbol_display_name(
+        symbol,
+        &settings.label_template,
+        settings.strip_market_suffix,
+    );
+    let separator = &settings.title_separator;
+    let price_text = match (trend, price) {
+        (_, Some(price)) => format_price(settings, symbol, price),
+        _ => settings.no_data_placeholder.clone(),
+    };
+    let price_text = if settings.fixed_width_title {
+        pad_price_text(symbol, &price_text, settings.title_integer_digits)
+    } else {
+        price_text
+    };
+    format!("{name}{separator}{price_text}")
+}
+
+// 按假设的整数位数在价格前补齐数字宽度空格（U+2007，与数字等宽），减少标题宽度跳动；
+// 超出假设宽度时原样返回，不截断。
+fn pad_price_text(symbol: &SymbolItem, price_text: &str, integer_digits: usize) -> String {
+    let prefix_chars = symbol
+        .currency_symbol
+        .as_deref()
+        .map_or(0, |prefix| prefix.chars().count());
+    // 整数位 + 小数点 + 小数位 + 货币符号。
+    let width = integer_digits + 1 + price_decimals(symbol) + prefix_chars;
+    let padding = width.saturating_sub(price_text.chars().count());
+    format!("{}{price_text}", "\u{2007}".repeat(padding))
+}
+
+// 渲染当前展示品类的标题与图标，批量刷新、焦点刷新与轮播切换共用：
+// 出错时标题加错误提示符，没有图标数据时显示等待图标。返回不含提示符的标题。
+fn render_display_symbol(
+    view: &mut TrayView,
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    price: Option<f64>,
+    trend: Option<&str>,
+    icon: Option<IconKind>,
+    last_error: Option<&FetchError>,
+) -> String {
+    let base = format_title(settings, symbol, price, trend);
+    // 品类休市时不加错误提示，保留最后的标题。
+    let title = if last_error.is_some() && !market_closed(symbol) {
+        error_title(&settings.error_indicator, &base)
+    } else {
+        base.clone()
+    };
+    view.set_title(title);
+    view.set_icon(icon.unwrap_or(IconKind::Pending));
+    base
+}
+
+// 标题价格的指数平滑：shown = alpha * raw + (1 - alpha) * 上次平滑价，减少末位数字跳动。
+// 每次拿到新数据时推进一次，价格不再变化时逐步收敛到原始价；未设置 alpha 时清除缓存。
+fn advance_smoothed_price(
+    smoothed: &mut HashMap<String, f64>,
+    code: &str,
+    raw: f64,
+    alpha: Option<f64>,
+) {
+    let Some(alpha) = alpha else {
+        smoothed.remove(code);
+        return;
+    };
+    let shown = match smoothed.get(code) {
+        Some(shown) => alpha * raw + (1.0 - alpha) * shown,
+        None => raw,
+    };
+    smoothed.insert(code.to_string(), shown);
+}
+
+// 标题渲染时读取平滑价，只读不推进；未设置 alpha 或尚无缓存时直接返回原始价。
+fn smoothed_title_price(
+    smoothed: &HashMap<String, f64>,
+    code: &str,
+    raw: f64,
+    alpha: Option<f64>,
+) -> f64 {
+    alpha
+        .and_then(|_| smoothed.get(code).copied())
+        .unwrap_or(raw)
+}
+
+// 标题价格的最小变动阈值：按展示数值（含反向报价）与上次标题价比较，变动不足 min_change 时沿用上次标题价。
+// held 缓存 {code -> 上次标题价}，阈值为 0 时始终更新；仅影响标题，tooltip 与提醒仍使用最新价格。
+fn hold_title_price(
+    held: &mut HashMap<String, f64>,
+    symbol: &SymbolItem,
+    price: f64,
+    min_change: f64,
+) -> f64 {
+    if min_change > 0.0 {
+        if let Some(last) = held.get(&symbol.code).copied() {
+            let moved = match (display_value(symbol, last), display_value(symbol, price)) {
+                (Some(last), Some(current)) => (current - last).abs(),
+                _ => f64::INFINITY,
+            };
+            if moved < min_change {
+                return last;
+            }
+        }
     }
+    held.insert(symbol.code.clone(), price);
+    price
+}
+
+// 标题使用的价格：先取平滑价，再按最小变动阈值决定是否沿用上次标题价。
+fn title_price(
+    smoothed: &HashMap<String, f64>,
+    held: &mut HashMap<String, f64>,
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    raw: f64,
+) -> f64 {
+    let shown = smoothed_title_price(smoothed, &symbol.code, raw, settings.smoothing_alpha);
+    hold_title_price(held, symbol, shown, settings.min_title_change)
 }
 
 // 根据轮播/固定模式选出当前要展示的品类。
@@ -708,7 +3829,8 @@ fn pick_display_symbol<'a>(
         return None;
     }
     match settings.display_mode {
-        DisplayMode::Rotate => settings.symbols.get(rotate_index),
+        // 最大波动模式下 rotate_index 由每次刷新时的涨跌幅计算得出。
+        DisplayMode::Rotate | DisplayMode::TopMover => settings.symbols.get(rotate_index),
         DisplayMode::Fixed => {
             if let Some(code) = settings.fixed_symbol.as_ref() {
                 settings.symbols.iter().find(|s| &s.code == code)
@@ -719,101 +3841,1130 @@ fn pick_display_symbol<'a>(
     }
 }
 
-// 启动异步轮询任务，负责请求行情并更新托盘显示。
-fn start_polling(tray: tauri::tray::TrayIcon, settings_handle: Arc<Mutex<QuoteSettings>>) {
+// 相对基准价的涨跌幅（百分比），基准为 0 时视为 0。
+fn change_percent(base: f64, price: f64) -> f64 {
+    if base == 0.0 {
+        0.0
+    } else {
+        (price - base) / base * 100.0
+    }
+}
+
+// 跳变检测：相对上次价格变化超过阈值时暂不采用并记为可疑价格，返回 false；
+// 连续两次读数彼此一致时才接受新的价位。未设置阈值或没有上次价格时直接采用。
+fn accept_price(
+    suspect_prices: &mut HashMap<String, f64>,
+    code: &str,
+    last: Option<f64>,
+    price: f64,
+    max_jump_percent: Option<f64>,
+) -> bool {
+    if let (Some(limit), Some(last)) = (max_jump_percent, last) {
+        if change_percent(last, price).abs() > limit {
+            let confirmed = suspect_prices
+                .get(code)
+                .is_some_and(|pending| change_percent(*pending, price).abs() <= limit);
+            if !confirmed {
+                log_line(&format!(
+                    "[xau-tray] suspicious jump for {code}: {last} -> {price}, rejected"
+                ));
+                suspect_prices.insert(code.to_string(), price);
+                return false;
+            }
+        }
+    }
+    suspect_prices.remove(code);
+    true
+}
+
+// 根据相对开盘价的涨跌幅判断趋势，幅度小于阈值（百分比）时视为平盘。
+fn trend_symbol(price: f64, open: f64, flat_threshold_percent: f64) -> &'static str {
+    if open != 0.0 && ((price - open) / open * 100.0).abs() < flat_threshold_percent {
+        "—"
+    } else if price > open {
+        "▲"
+    } else if price < open {
+        "▼"
+    } else {
+        "—"
+    }
+}
+
+// 将 tooltip 控制在最大行数内：错误信息、当前展示品类与末尾统计始终保留，
+// 其余品类按顺序尽量放入，放不下的折叠为"… 其余 N 项"。
+fn fit_tooltip_lines(
+    error_lines: Vec<String>,
+    symbol_lines: Vec<Vec<String>>,
+    displayed: Option<usize>,
+    footer_lines: Vec<String>,
+    max_lines: usize,
+    locale: &str,
+) -> Vec<String> {
+    let total =
+        error_lines.len() + symbol_lines.iter().map(Vec::len).sum::<usize>() + footer_lines.len();
+    if total <= max_lines {
+        let mut lines = error_lines;
+        lines.extend(symbol_lines.into_iter().flatten());
+        lines.extend(footer_lines);
+        return lines;
+    }
+    // 预留一行给折叠提示。
+    let mut budget = max_lines.saturating_sub(error_lines.len() + footer_lines.len() + 1);
+    let mut keep = vec![false; symbol_lines.len()];
+    if let Some(index) = displayed.filter(|index| *index < symbol_lines.len()) {
+        keep[index] = true;
+        budget = budget.saturating_sub(symbol_lines[index].len());
+    }
+    for (index, group) in symbol_lines.iter().enumerate() {
+        if !keep[index] && group.len() <= budget {
+            keep[index] = true;
+            budget -= group.len();
+        }
+    }
+    let omitted = keep.iter().filter(|kept| !**kept).count();
+    let mut lines = error_lines;
+    for (group, kept) in symbol_lines.into_iter().zip(keep) {
+        if kept {
+            lines.extend(group);
+        }
+    }
+    if omitted > 0 {
+        lines.push(tr(locale, "omitted").replace("{count}", &omitted.to_string()));
+    }
+    lines.extend(footer_lines);
+    lines
+}
+
+// 代码连续无数据达到阈值、且看起来属于另一类接口时，生成切换接口的提示。
+// 混合品类的代码不直接请求，不做提示。
+fn empty_data_hint(
+    symbol: &SymbolItem,
+    api_type: ApiType,
+    empty_count: u32,
+    locale: &str,
+) -> Option<String> {
+    if symbol.blend.is_some() || empty_count < EMPTY_DATA_HINT_THRESHOLD {
+        return None;
+    }
+    let guess = ApiType::guess_for_code(&symbol.code);
+    if guess == api_type {
+        return None;
+    }
+    Some(
+        tr(locale, "empty_data_hint")
+            .replace("{code}", &symbol.code)
+            .replace("{api}", guess.label(locale)),
+    )
+}
+
+// 计算本轮 token 的尝试顺序：从上次成功的 token 开始依次排列，
+// 跳过冷却期内失败过的 token；若全部在冷却中，则按原顺序全部重试。
+fn token_attempt_order(
+    tokens: &[String],
+    preferred: usize,
+    failures: &HashMap<String, Instant>,
+) -> Vec<usize> {
+    let cooldown = Duration::from_secs(TOKEN_COOLDOWN_SECONDS);
+    let all: Vec<usize> = (0..tokens.len())
+        .map(|offset| (preferred + offset) % tokens.len())
+        .collect();
+    let healthy: Vec<usize> = all
+        .iter()
+        .copied()
+        .filter(|index| {
+            failures
+                .get(&tokens[*index])
+                .is_none_or(|failed_at| failed_at.elapsed() >= cooldown)
+        })
+        .collect();
+    if healthy.is_empty() {
+        all
+    } else {
+        healthy
+    }
+}
+
+// 计算出错后的下一次退避秒数：首次为刷新间隔的 multiplier 倍，之后翻倍，不超过上限且不低于刷新间隔。
+fn next_error_backoff(
+    current: u64,
+    base_refresh_seconds: u64,
+    multiplier: u64,
+    max_seconds: u64,
+) -> u64 {
+    let next = if current == 0 {
+        base_refresh_seconds * multiplier
+    } else {
+        current * 2
+    };
+    next.min(max_seconds).max(base_refresh_seconds)
+}
+
+// 距离下一个 interval_secs 整倍数时刻（按系统时钟）的等待时间，多个实例据此在同一时刻刷新。
+fn delay_to_clock_boundary(interval_secs: u64) -> Duration {
+    let interval_ms = interval_secs.max(1) * 1000;
+    let now_ms = chrono::Local::now().timestamp_millis().max(0) as u64;
+    let next_ms = (now_ms / interval_ms + 1) * interval_ms;
+    Duration::from_millis(next_ms - now_ms)
+}
+
+// 以请求开始时间为基准计算下一次刷新时间点；若请求耗时已越过若干个间隔，
+// 直接跳过这些刷新点，返回新的时间点与跳过的次数。
+fn schedule_next_refresh(started: Instant, interval: Duration, now: Instant) -> (Instant, u32) {
+    if interval.is_zero() {
+        return (now, 0);
+    }
+    let mut next = started + interval;
+    let mut skipped = 0;
+    while next <= now {
+        next += interval;
+        skipped += 1;
+    }
+    (next, skipped)
+}
+
+// 品类列表变化后重新定位轮播位置：列表未变时保持原位，
+// 变化时优先跟随之前展示的品类，已被移除则回到第一个。
+fn realign_rotate_index(previous: &[String], current: &[String], index: usize) -> usize {
+    if previous == current {
+        return if index < current.len() { index } else { 0 };
+    }
+    previous
+        .get(index)
+        .and_then(|code| current.iter().position(|c| c == code))
+        .unwrap_or(0)
+}
+
+// 记录最新价格并返回 window 次刷新前的价格；window 为 0 或历史不足时返回 None。
+fn momentum_reference(history: &mut VecDeque<f64>, price: f64, window: usize) -> Option<f64> {
+    if window == 0 {
+        history.clear();
+        return None;
+    }
+    history.push_back(price);
+    while history.len() > window + 1 {
+        history.pop_front();
+    }
+    if history.len() == window + 1 {
+        history.front().copied()
+    } else {
+        None
+    }
+}
+
+// 与 momentum_reference 返回相同的参考价但不记录新价格，供两次批量刷新之间的焦点刷新使用。
+fn peek_momentum_reference(history: Option<&VecDeque<f64>>, window: usize) -> Option<f64> {
+    let history = history?;
+    if window == 0 || history.len() < window {
+        return None;
+    }
+    history.get(history.len() - window).copied()
+}
+
+// 计算品类的涨跌方向：开启入场价趋势且设置了入场价时与入场价比较，其次与动量参考价比较，否则与开盘价比较。
+// 反向报价时价格上涨即显示值下跌，与空头视角同时开启时相互抵消；
+// 与当前 K 线开盘价比较且 K 线刚开始时，平盘多半只是时间太短，沿用上一次的方向。
+fn quote_trend(
+    settings: &QuoteSettings,
+    symbol: &SymbolItem,
+    quote: &Quote,
+    momentum: Option<f64>,
+    previous: Option<&String>,
+) -> &'static str {
+    let entry = symbol.entry_price.filter(|_| settings.trend_from_entry);
+    let reference = entry.or(momentum).unwrap_or(quote.open);
+    let mut trend = trend_symbol(quote.price, reference, settings.flat_threshold_percent);
+    if symbol.invert_trend != symbol.invert_price {
+        trend = invert_trend_symbol(trend);
+    }
+    let compares_open = entry.is_none() && momentum.is_none() && settings.baseline_offset == 0;
+    if trend == "—" && compares_open && candle_just_started(quote.timestamp) {
+        trend = carry_over_trend(previous, trend);
+    }
+    trend
+}
+
+// 品类本次没有数据：默认立即切换为等待图标；开启保持图标时，
+// 连续缺失达到 HOLD_ICON_MAX_MISSES 次前沿用上一次的方向图标，避免不稳定的网络下图标闪烁。
+fn mark_icon_missing(
+    icon_kinds: &mut HashMap<String, IconKind>,
+    icon_misses: &mut HashMap<String, u32>,
+    code: &str,
+    hold_last_icon: bool,
+) {
+    let misses = icon_misses.entry(code.to_string()).or_insert(0);
+    *misses += 1;
+    if !hold_last_icon || *misses >= HOLD_ICON_MAX_MISSES || !icon_kinds.contains_key(code) {
+        icon_kinds.insert(code.to_string(), IconKind::Pending);
+    }
+}
+
+// K 线（时间戳为开始时间，秒）是否在 FRESH_CANDLE_SECONDS 内刚开始。
+fn candle_just_started(timestamp: u64) -> bool {
+    let now = chrono::Utc::now().timestamp() as u64;
+    now.saturating_sub(timestamp) < FRESH_CANDLE_SECONDS
+}
+
+// 上一次的趋势为涨或跌时沿用，否则保持当前趋势。
+fn carry_over_trend(previous: Option<&String>, current: &'static str) -> &'static str {
+    match previous.map(String::as_str) {
+        Some("▲") => "▲",
+        Some("▼") => "▼",
+        _ => current,
+    }
+}
+
+// 反转涨跌符号（空头视角），平盘保持不变。
+fn invert_trend_symbol(trend: &'static str) -> &'static str {
+    match trend {
+        "▲" => "▼",
+        "▼" => "▲",
+        other => other,
+    }
+}
+
+// 找出相对开盘价涨跌幅绝对值最大的品类下标；并列时取列表中靠前者。
+fn top_mover_index(
+    symbols: &[SymbolItem],
+    last_prices: &HashMap<String, f64>,
+    last_opens: &HashMap<String, f64>,
+) -> Option<usize> {
+    let mut best: Option<(usize, f64)> = None;
+    for (index, symbol) in symbols.iter().enumerate() {
+        let (Some(price), Some(open)) =
+            (last_prices.get(&symbol.code), last_opens.get(&symbol.code))
+        else {
+            continue;
+        };
+        if *open == 0.0 {
+            continue;
+        }
+        let change = ((price - open) / open).abs();
+        if best.is_none_or(|(_, max)| change > max) {
+            best = Some((index, change));
+        }
+    }
+    best.map(|(index, _)| index)
+}
+
+// 判断当前小时是否处于静默时段 [start, end)，支持跨午夜（如 22 -> 6）。
+fn in_quiet_hours(start: u8, end: u8, hour: u32) -> bool {
+    let (start, end) = (start as u32, end as u32);
+    if start < end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+// 前端提醒横幅的事件载荷：本地化后的提示文本与原始错误。
+#[derive(Serialize, Clone, Debug)]
+struct AlertPayload {
+    message: String,
+    error: Option<FetchError>,
+}
+
+// 解析交易时段的开收盘时间与时区，任一无效时返回 None。
+fn parse_session(
+    session: &MarketSession,
+) -> Option<(chrono::NaiveTime, chrono::NaiveTime, chrono_tz::Tz)> {
+    let open = chrono::NaiveTime::parse_from_str(&session.open, "%H:%M").ok()?;
+    let close = chrono::NaiveTime::parse_from_str(&session.close, "%H:%M").ok()?;
+    let timezone = session.timezone.parse::<chrono_tz::Tz>().ok()?;
+    Some((open, close, timezone))
+}
+
+// 是否为全天候交易品类：显式配置优先，否则商品接口（含加密货币）视为全天候，股票接口视为有收盘。
+fn symbol_always_open(symbol: &SymbolItem, api_type: ApiType) -> bool {
+    symbol.always_open.unwrap_or(api_type == ApiType::Commodity)
+}
+
+// 判断品类当前是否休市：按交易所时区比较，周末休市，收盘早于开盘时视为跨午夜时段；未设置时段视为全天交易。
+fn market_closed(symbol: &SymbolItem) -> bool {
+    use chrono::Datelike;
+    let Some((open, close, timezone)) = symbol.session.as_ref().and_then(parse_session) else {
+        return false;
+    };
+    let local = chrono::Utc::now().with_timezone(&timezone);
+    if local.weekday().number_from_monday() > 5 {
+        return true;
+    }
+    let time = local.time();
+    let trading = if open < close {
+        time >= open && time < close
+    } else {
+        time >= open || time < close
+    };
+    !trading
+}
+
+// 业务错误多次重复时提醒用户：通知前端并弹出设置窗口。
+fn alert_business_error(app: &AppHandle, err: &FetchError, locale: &str) {
+    log_line(&format!(
+        "[xau-tray] repeated business error: {} msg={:?}",
+        err.detail, err.msg
+    ));
+    let message = tr(locale, "business_error_alert")
+        .replace("{msg}", err.msg.as_deref().unwrap_or(&err.detail));
+    let _ = app.emit(
+        "business-error-alert",
+        AlertPayload {
+            message,
+            error: Some(err.clone()),
+        },
+    );
+    if let Some(win) = app.get_webview_window("main") {
+        let _ = win.show();
+        let _ = win.request_user_attention(Some(tauri::UserAttentionType::Informational));
+    }
+}
+
+// 托盘状态图标种类，用于判断图标是否需要重新设置。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum IconKind {
+    Up,
+    Down,
+    Flat,
+    Pending,
+    Error,
+}
+
+impl IconKind {
+    // 根据有数据时的趋势符号选择图标：涨/跌/平（涨跌幅在 flat_threshold_percent 内）。
+    // 无数据或加载中由调用方直接使用 Pending，不与平盘混用。
+    fn from_trend(trend: &str) -> Self {
+        match trend {
+            "▲" => Self::Up,
+            "▼" => Self::Down,
+            _ => Self::Flat,
+        }
+    }
+}
+
+// 托盘显示的包装：记录最近一次实际设置的标题与图标，内容未变化时跳过调用，减少菜单栏闪烁。
+struct TrayView {
+    tray: tauri::tray::TrayIcon,
+    up_icon: Option<Image<'static>>,
+    down_icon: Option<Image<'static>>,
+    flat_icon: Option<Image<'static>>,
+    pending_icon: Option<Image<'static>>,
+    error_icon: Option<Image<'static>>,
+    last_shown_title: Option<String>,
+    last_shown_icon: Option<IconKind>,
+    // 标题的最大显示宽度，超出时截断并加省略号。
+    max_title_chars: Option<usize>,
+}
+
+// 按显示宽度截断标题（中日韩等宽字符计 2 列），超出时保留前缀并追加 "…"。
+fn truncate_title(title: &str, max_width: usize) -> String {
+    use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+    if title.width() <= max_width {
+        return title.to_string();
+    }
+    let budget = max_width.saturating_sub(1);
+    let mut width = 0;
+    let mut truncated = String::new();
+    for c in title.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > budget {
+            break;
+        }
+        width += char_width;
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
+}
+
+// 按名称加载状态图标；缩放比例不低于 2 时使用 @2x 版本，避免高分屏下模糊。
+fn load_status_icon(name: &str, scale: f64) -> Option<Image<'static>> {
+    let hidpi = scale >= 2.0;
+    let bytes: &[u8] = match (name, hidpi) {
+        ("up", false) => include_bytes!("../icons/status/up.png"),
+        ("up", true) => include_bytes!("../icons/status/up@2x.png"),
+        ("down", false) => include_bytes!("../icons/status/down.png"),
+        ("down", true) => include_bytes!("../icons/status/down@2x.png"),
+        ("pending", false) => include_bytes!("../icons/status/pending.png"),
+        ("pending", true) => include_bytes!("../icons/status/pending@2x.png"),
+        ("flat", false) => include_bytes!("../icons/status/flat.png"),
+        ("flat", true) => include_bytes!("../icons/status/flat@2x.png"),
+        ("error", false) => include_bytes!("../icons/status/error.png"),
+        ("error", true) => include_bytes!("../icons/status/error@2x.png"),
+        _ => return None,
+    };
+    Image::from_bytes(bytes).ok().map(|img| img.to_owned())
+}
+
+// 获取主显示器的缩放比例，无法获取时按 1 倍处理。
+fn display_scale_factor(app: &AppHandle) -> f64 {
+    app.primary_monitor()
+        .ok()
+        .flatten()
+        .map(|monitor| monitor.scale_factor())
+        .unwrap_or(1.0)
+}
+
+impl TrayView {
+    fn new(tray: tauri::tray::TrayIcon, scale: f64) -> Self {
+        // 预加载托盘图标（涨/跌/平/等待/错误）。
+        Self {
+            tray,
+            up_icon: load_status_icon("up", scale),
+            down_icon: load_status_icon("down", scale),
+            flat_icon: load_status_icon("flat", scale),
+            pending_icon: load_status_icon("pending", scale),
+            error_icon: load_status_icon("error", scale),
+            last_shown_title: None,
+            last_shown_icon: None,
+            max_title_chars: None,
+        }
+    }
+
+    // 标题与上次一致时不重复设置。
+    fn set_title(&mut self, title: String) {
+        let title = match self.max_title_chars {
+            Some(max_width) => truncate_title(&title, max_width),
+            None => title,
+        };
+        if self.last_shown_title.as_deref() == Some(title.as_str()) {
+            return;
+        }
+        let _ = self.tray.set_title(Some(title.clone()));
+        self.last_shown_title = Some(title);
+    }
+
+    fn set_tooltip(&self, tooltip: String) {
+        let _ = self.tray.set_tooltip(Some(tooltip));
+    }
+
+    // 图标种类与上次一致时不重复设置。
+    fn set_icon(&mut self, kind: IconKind) {
+        if self.last_shown_icon == Some(kind) {
+            return;
+        }
+        let icon = match kind {
+            IconKind::Up => self.up_icon.clone(),
+            IconKind::Down => self.down_icon.clone(),
+            IconKind::Flat => self.flat_icon.clone(),
+            IconKind::Pending => self.pending_icon.clone(),
+            IconKind::Error => self.error_icon.clone(),
+        };
+        if let Some(icon) = icon {
+            let _ = self.tray.set_icon(Some(icon));
+            self.last_shown_icon = Some(kind);
+        }
+    }
+}
+
+// 转义 Prometheus 标签值中的反斜杠、双引号与换行。
+fn escape_metric_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// 以 Prometheus 文本格式输出运行指标。
+fn render_metrics(metrics: &PollMetrics, uptime_seconds: u64) -> String {
+    let mut out = String::new();
+    let mut gauge = |name: &str, kind: &str, help: &str, value: u64| {
+        out.push_str(&format!(
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+        ));
+    };
+    gauge(
+        "xau_tray_uptime_seconds",
+        "gauge",
+        "Seconds since start.",
+        uptime_seconds,
+    );
+    gauge(
+        "xau_tray_requests_total",
+        "counter",
+        "Quote requests sent.",
+        metrics.requests_total,
+    );
+    gauge(
+        "xau_tray_request_failures_total",
+        "counter",
+        "Quote requests that failed.",
+        metrics.failures_total,
+    );
+    gauge(
+        "xau_tray_rejected_prices_total",
+        "counter",
+        "Prices held back by jump detection.",
+        metrics.rejected_prices_total,
+    );
+    gauge(
+        "xau_tray_backoff_seconds",
+        "gauge",
+        "Current error backoff interval.",
+        metrics.backoff_seconds,
+    );
+    gauge(
+        "xau_tray_last_latency_ms",
+        "gauge",
+        "Latency of the last quote request.",
+        metrics.last_latency_ms,
+    );
+    out.push_str("# HELP xau_tray_last_price Last known price per symbol.\n");
+    out.push_str("# TYPE xau_tray_last_price gauge\n");
+    let mut codes: Vec<&String> = metrics.last_prices.keys().collect();
+    codes.sort();
+    for code in codes {
+        out.push_str(&format!(
+            "xau_tray_last_price{{code=\"{}\"}} {}\n",
+            escape_metric_label(code),
+            metrics.last_prices[code]
+        ));
+    }
+    out
+}
+
+// 在 127.0.0.1 上启动极简 HTTP 服务，任意路径均返回指标文本。
+fn start_metrics_server(port: u16, metrics_handle: Arc<Mutex<PollMetrics>>) {
     tauri::async_runtime::spawn(async move {
-        // 预加载托盘图标（涨/跌/等待）。
-        let up_icon = Image::from_bytes(include_bytes!("../icons/status/up.png"))
-            .ok()
-            .map(|img| img.to_owned());
-        let down_icon = Image::from_bytes(include_bytes!("../icons/status/down.png"))
-            .ok()
-            .map(|img| img.to_owned());
-        let pending_icon = Image::from_bytes(include_bytes!("../icons/status/pending.png"))
-            .ok()
-            .map(|img| img.to_owned());
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                log_line(&format!("[xau-tray] metrics server bind failed: {err}"));
+                return;
+            }
+        };
+        log_line(&format!(
+            "[xau-tray] metrics server listening on 127.0.0.1:{port}"
+        ));
+        let started = Instant::now();
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    log_line(&format!("[xau-tray] metrics accept failed: {err}"));
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    continue;
+                }
+            };
+            let body = render_metrics(
+                &lock_or_recover(&metrics_handle),
+                started.elapsed().as_secs(),
+            );
+            tauri::async_runtime::spawn(async move {
+                // 只读取请求头的开头部分，不解析路径。
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+}
+
+// 启动异步轮询任务，负责请求行情并更新托盘显示。
+fn start_polling(
+    app: AppHandle,
+    tray: tauri::tray::TrayIcon,
+    settings_handle: Arc<Mutex<QuoteSettings>>,
+    metrics_handle: Arc<Mutex<PollMetrics>>,
+    shutdown: Arc<ShutdownSignal>,
+    cancel: CancellationToken,
+) {
+    // 推送连接的取消令牌挂在轮询任务之下，轮询重启或退出时一并断开。
+    let stream_parent = cancel.clone();
+    let polling = async move {
+        let mut view = TrayView::new(tray, display_scale_factor(&app));
 
         // 缓存最近一次的价格与趋势，避免空窗期导致显示断层。
         let mut last_prices: HashMap<String, f64> = HashMap::new();
+        let mut last_volumes: HashMap<String, f64> = HashMap::new();
+        let mut last_opens: HashMap<String, f64> = HashMap::new();
+        // 标题平滑价格缓存：{code -> 平滑价}，每次拿到新数据时推进，仅影响标题。
+        let mut smoothed_prices: HashMap<String, f64> = HashMap::new();
+        // 标题价格变动阈值缓存：{code -> 上次标题价}，仅影响标题。
+        let mut held_title_prices: HashMap<String, f64> = HashMap::new();
+        // 最新成交价缓存，仅保存当前展示品类，用于标题。
+        let mut tick_prices: HashMap<String, f64> = HashMap::new();
+        // 本次刷新中缺少部分成分数据的混合品类。
+        let mut blend_partial: HashSet<String> = HashSet::new();
+        // 各品类连续没有数据的次数，用于保持图标时判断何时切换为等待图标。
+        let mut icon_misses: HashMap<String, u32> = HashMap::new();
+        // 跳变待确认的价格 {code -> 被拒绝的新价格}，下一次读数与之一致时才接受。
+        let mut suspect_prices: HashMap<String, f64> = HashMap::new();
         let mut trends: HashMap<String, String> = HashMap::new();
+        // 各品类最近几次刷新的价格，用于动量趋势。
+        let mut price_history: HashMap<String, VecDeque<f64>> = HashMap::new();
+        // 焦点品类下一次快速刷新的时间点。
+        let mut next_focus_refresh = Instant::now();
+        // 昨收缓存：{code -> (上一交易日收盘价, 最新日线时间戳)}。
+        let mut prev_closes: HashMap<String, (f64, u64)> = HashMap::new();
+        let mut next_prev_close_check = Instant::now();
+        // 各品类的日内 VWAP 累计状态。
+        let mut vwap_states: HashMap<String, VwapState> = HashMap::new();
+        // 展示品类的简单均线缓存 {code -> (周期, 均值)}，K 线不足周期数时均值为 None。
+        let mut ma_values: HashMap<String, (u32, Option<f64>)> = HashMap::new();
+        // 各品类对应的图标：有数据时为涨/跌/平，无数据时为等待。
+        let mut icon_kinds: HashMap<String, IconKind> = HashMap::new();
         let mut rotate_index: usize = 0;
+        // 上一轮看到的展示锁定品类。
+        let mut last_display_lock: Option<String> = None;
+        // 上一轮看到的临时加速状态。
+        let mut last_turbo: Option<(Instant, u64)> = None;
+        // 上一轮快照中的品类代码，用于检测设置变更。
+        let mut last_symbol_codes: Vec<String> = Vec::new();
         let mut last_title = String::new();
         let mut last_error: Option<FetchError> = None;
         let mut error_backoff_seconds: u64 = 0;
-        // 记录当前 token 的轮换位置，出错时顺序切换。
+        // 网络错误退避期间的下一次连通性探测时间，以及上一次探测是否失败。
+        let mut next_probe = Instant::now();
+        let mut probe_failed = false;
+        // 应用数据目录的下一次可写性自检时间，以及最近一次自检的错误。
+        let mut next_data_dir_check = Instant::now();
+        let mut data_dir_error: Option<String> = None;
+        // 最近一次成功的 token 下标，下次请求优先使用。
         let mut token_index: usize = 0;
+        // 各 token 最近一次业务失败的时间，冷却期内跳过。
+        let mut token_failures: HashMap<String, Instant> = HashMap::new();
         let mut next_refresh = Instant::now();
         let mut next_rotate = Instant::now();
+        // 锚点价格缓存：{code -> (锚点开盘价, K 线时间戳)}，仅在周期切换时刷新。
+        let mut anchor_prices: HashMap<String, (f64, u64)> = HashMap::new();
+        let mut cached_anchor: Option<ChangeAnchor> = None;
+        let mut next_anchor_check = Instant::now();
+        // 连续相同业务错误的计数，达到阈值后只提醒一次，成功后重置。
+        let mut business_error_streak: u32 = 0;
+        let mut business_error_msg: Option<String> = None;
+        let mut business_error_alerted = false;
+        // 各代码连续无数据的次数，用于提示接口类型可能选错。
+        let mut empty_counts: HashMap<String, u32> = HashMap::new();
+        // 连续成功/失败的请求次数，出现相反结果时清零。
+        let mut consecutive_successes: u32 = 0;
+        let mut consecutive_failures: u32 = 0;
+        // 最近一次成功批量请求的元信息。
+        let mut last_fetch_meta: Option<FetchMeta> = None;
+        // 最近一次成功刷新的本地时间，显示在 tooltip 末尾。
+        let mut last_success_at: Option<chrono::DateTime<chrono::Local>> = None;
+        // 轮换请求顺序时本次的起始偏移。
+        let mut request_offset: usize = 0;
+        // 接口拒绝过大批次后记住的可用批次大小，未设置时一次请求全部品类。
+        let mut batch_limit = BatchLimit::default();
+        // 推送连接：取消令牌与订阅的代码；连接成功后批量请求降频，失败后按退避时间重连，期间回退为轮询。
+        let mut stream: Option<(CancellationToken, Vec<String>)> = None;
+        let mut stream_connected = false;
+        let mut stream_backoff_seconds: u64 = 0;
+        let mut stream_retry_at = Instant::now();
+        // 检测到代理而跳过推送时只记录一次日志。
+        let mut stream_proxy_warned = false;
+        let (stream_sender, mut stream_receiver) =
+            tokio::sync::mpsc::unbounded_channel::<StreamEvent>();
+        // 睡眠期间被推送唤醒时收到的事件，下一轮处理。
+        let mut woken_event: Option<StreamEvent> = None;
 
         loop {
+            // 收到退出信号：当前轮次已结束，保存状态后退出应用。
+            if shutdown.is_requested() {
+                let snapshot = PollStateSnapshot {
+                    saved_at: chrono::Local::now().to_rfc3339(),
+                    prices: &last_prices,
+                    trends: &trends,
+                    last_error: last_error.as_ref(),
+                    consecutive_failures,
+                };
+                if let Err(err) = save_poll_state(&app, &snapshot) {
+                    log_line(&format!("[xau-tray] save poll state failed: {err}"));
+                }
+                exit_after_pending_save(&app);
+                break;
+            }
+
             // 读取当前配置的快照，避免长时间持有锁。
-            let settings = settings_handle.lock().unwrap().clone();
+            let mut settings = lock_or_recover(&settings_handle).clone();
+            // 停用的品类只保留在配置中，本轮快照里直接去掉，不参与请求、轮播与展示。
+            settings.symbols.retain(|symbol| symbol.enabled);
+            // 标题宽度上限随设置实时生效。
+            view.max_title_chars = settings.max_title_chars;
+            // 配置了组合权重时，追加组合指数虚拟品类。
+            if !settings.portfolio.is_empty() {
+                let locale = effective_locale(&settings.locale);
+                let portfolio = portfolio_symbol(&settings.portfolio, locale);
+                settings.symbols.push(portfolio);
+            }
+            // 前端锁定展示品类时，仅在本轮快照中按固定模式展示，不修改持久化配置。
+            let display_lock = lock_or_recover(&app.state::<AppState>().display_lock).clone();
+            if let Some(code) = display_lock.as_ref() {
+                if settings.symbols.iter().any(|s| &s.code == code) {
+                    settings.display_mode = DisplayMode::Fixed;
+                    settings.fixed_symbol = Some(code.clone());
+                }
+            }
+            // 锁定状态变化后立即刷新一次，让托盘尽快切换到目标品类。
+            if display_lock != last_display_lock {
+                last_display_lock = display_lock;
+                next_refresh = Instant::now();
+            }
+            let locale = effective_locale(&settings.locale);
             let now = Instant::now();
             let rotate_interval = Duration::from_secs(settings.rotate_seconds);
-            let base_refresh_seconds = settings.refresh_seconds;
+            // 左键点击切换品类：轮播模式下立即切到下一个，其他模式忽略。
+            if app
+                .state::<AppState>()
+                .next_symbol
+                .swap(false, Ordering::SeqCst)
+                && settings.display_mode == DisplayMode::Rotate
+            {
+                next_rotate = now;
+            }
+            // 手动刷新：立即请求，并清零错误退避。
+            if app
+                .state::<AppState>()
+                .refresh_requested
+                .swap(false, Ordering::SeqCst)
+            {
+                error_backoff_seconds = 0;
+                probe_failed = false;
+                next_refresh = now;
+            }
+            // 临时加速：有效期内使用更短的刷新间隔，到期后自动恢复；状态变化时立即刷新一次。
+            let turbo = {
+                let state = app.state::<AppState>();
+                let mut turbo = lock_or_recover(&state.turbo);
+                if turbo.is_some_and(|(until, _)| now >= until) {
+                    *turbo = None;
+                    log_line("[xau-tray] turbo mode ended");
+                }
+                *turbo
+            };
+            if turbo != last_turbo {
+                last_turbo = turbo;
+                next_refresh = Instant::now();
+            }
+            let base_refresh_seconds = match turbo {
+                Some((_, seconds)) => seconds.min(settings.refresh_seconds),
+                None => settings.refresh_seconds,
+            };
+
+            // 没有品类时，直接提示用户并进入短睡眠。
+            if settings.symbols.is_empty() {
+                view.set_title(tr(locale, "no_symbols").to_string());
+                view.set_tooltip(tr(locale, "no_symbols_tooltip").to_string());
+                view.set_icon(IconKind::Pending);
+                shutdown.sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+
+            // 品类列表变化时，尽量继续展示原来的品类，否则从头开始轮播。
+            let symbol_codes: Vec<String> =
+                settings.symbols.iter().map(|s| s.code.clone()).collect();
+            rotate_index = realign_rotate_index(&last_symbol_codes, &symbol_codes, rotate_index);
+            // 品类列表变化（如切换自选列表）后立即刷新，避免新品类长时间显示为空。
+            if symbol_codes != last_symbol_codes {
+                next_refresh = Instant::now();
+            }
+            last_symbol_codes = symbol_codes;
+
+            // 静默时段：暂停请求，保留最后的数据并以 🌙 标记，到点后立即恢复。
+            if let Some((start, end)) = settings.quiet_hours {
+                use chrono::Timelike;
+                let local = chrono::Local::now();
+                if in_quiet_hours(start, end, local.hour()) {
+                    // 静默期间断开推送连接并丢弃积压的推送。
+                    if let Some((token, _)) = stream.take() {
+                        token.cancel();
+                    }
+                    stream_connected = false;
+                    woken_event = None;
+                    while stream_receiver.try_recv().is_ok() {}
+                    view.set_title(format!("🌙 {last_title}").trim_end().to_string());
+                    // 最多睡到下一个整分钟，保证跨过边界后及时恢复。
+                    let wait = 60 - u64::from(local.second()).min(59);
+                    shutdown.sleep(Duration::from_secs(wait)).await;
+                    next_refresh = Instant::now();
+                    continue;
+                }
+            }
+
+            // 推送模式：维持推送连接，订阅代码变化、切回轮询或 token 为空时断开。
+            let stream_tokens = parse_tokens(&settings.token);
+            let stream_codes = request_codes(&settings.symbols);
+            let want_stream = settings.transport == Transport::Stream
+                && !stream_tokens.is_empty()
+                && alltick_extras_enabled(&settings);
+            if stream
+                .as_ref()
+                .is_some_and(|(_, codes)| !want_stream || *codes != stream_codes)
+            {
+                if let Some((token, _)) = stream.take() {
+                    token.cancel();
+                }
+                stream_connected = false;
+            }
+            if want_stream
+                && stream.is_none()
+                && Instant::now() >= stream_retry_at
+                && stream_blocked_by_proxy(&settings)
+            {
+                if !stream_proxy_warned {
+                    log_line("[xau-tray] stream transport skipped: proxy active, using polling");
+                    stream_proxy_warned = true;
+                }
+                stream_retry_at =
+                    Instant::now() + Duration::from_secs(STREAM_KLINE_REFRESH_SECONDS);
+            } else if want_stream && stream.is_none() && Instant::now() >= stream_retry_at {
+                stream_proxy_warned = false;
+                let token = stream_tokens
+                    .get(token_index)
+                    .unwrap_or(&stream_tokens[0])
+                    .clone();
+                let child = stream_parent.child_token();
+                let guard = child.clone();
+                let sender = stream_sender.clone();
+                let task = run_price_stream(
+                    token,
+                    stream_codes.clone(),
+                    settings.api_type,
+                    settings.endpoints.clone(),
+                    sender.clone(),
+                );
+                tauri::async_runtime::spawn(async move {
+                    if let Some(Err(err)) = guard.run_until_cancelled(task).await {
+                        let _ = sender.send(StreamEvent::Closed(err));
+                    }
+                });
+                stream = Some((child, stream_codes));
+            }
+
+            // 处理推送事件：成交价直接更新缓存与趋势，当前展示品类立即刷新标题；
+            // 混合品类仍由批量请求更新。连接断开后按出错退避时间重连。
+            let mut stream_events: Vec<StreamEvent> = woken_event.take().into_iter().collect();
+            while let Ok(event) = stream_receiver.try_recv() {
+                stream_events.push(event);
+            }
+            let displayed_code =
+                pick_display_symbol(&settings, rotate_index).map(|s| s.code.clone());
+            let mut displayed_updated = false;
+            for event in stream_events {
+                match event {
+                    StreamEvent::Connected => {
+                        stream_connected = true;
+                        stream_backoff_seconds = 0;
+                    }
+                    StreamEvent::Closed(reason) => {
+                        if let Some((token, _)) = stream.take() {
+                            token.cancel();
+                        }
+                        stream_connected = false;
+                        stream_backoff_seconds = next_error_backoff(
+                            stream_backoff_seconds,
+                            base_refresh_seconds,
+                            settings.backoff_multiplier,
+                            settings.max_backoff_seconds,
+                        );
+                        stream_retry_at =
+                            Instant::now() + Duration::from_secs(stream_backoff_seconds);
+                        log_line(&format!(
+                            "[xau-tray] stream failed: {reason}, retry in {stream_backoff_seconds}s"
+                        ));
+                    }
+                    StreamEvent::Trade { code, price } => {
+                        // 推送价格同样经过跳变检测，可疑价格不写入缓存与标题。
+                        if !accept_price(
+                            &mut suspect_prices,
+                            &code,
+                            last_prices.get(&code).copied(),
+                            price,
+                            settings.max_jump_percent,
+                        ) {
+                            lock_or_recover(&metrics_handle).rejected_prices_total += 1;
+                            continue;
+                        }
+                        let symbols = settings
+                            .symbols
+                            .iter()
+                            .filter(|s| s.blend.is_none() && s.code == code);
+                        for symbol in symbols {
+                            let reference = symbol
+                                .entry_price
+                                .filter(|_| settings.trend_from_entry)
+                                .or_else(|| last_opens.get(&code).copied());
+                            if let Some(reference) = reference {
+                                let mut trend =
+                                    trend_symbol(price, reference, settings.flat_threshold_percent);
+                                // 反向报价时价格上涨即显示值下跌；与空头视角同时开启时相互抵消。
+                                if symbol.invert_trend != symbol.invert_price {
+                                    trend = invert_trend_symbol(trend);
+                                }
+                                trends.insert(code.clone(), trend.to_string());
+                                icon_kinds.insert(code.clone(), IconKind::from_trend(trend));
+                            }
+                            last_prices.insert(code.clone(), price);
+                            advance_smoothed_price(
+                                &mut smoothed_prices,
+                                &code,
+                                price,
+                                settings.smoothing_alpha,
+                            );
+                            tick_prices.remove(&code);
+                            displayed_updated |= displayed_code.as_ref() == Some(&code);
+                        }
+                    }
+                }
+            }
+            if displayed_updated {
+                if let Some(symbol) = pick_display_symbol(&settings, rotate_index) {
+                    let price = last_prices.get(&symbol.code).map(|raw| {
+                        title_price(
+                            &smoothed_prices,
+                            &mut held_title_prices,
+                            &settings,
+                            symbol,
+                            *raw,
+                        )
+                    });
+                    last_title = render_display_symbol(
+                        &mut view,
+                        &settings,
+                        symbol,
+                        price,
+                        trends.get(&symbol.code).map(String::as_str),
+                        icon_kinds.get(&symbol.code).copied(),
+                        last_error.as_ref(),
+                    );
+                }
+            }
 
-            // 没有品类时，直接提示用户并进入短睡眠。
-            if settings.symbols.is_empty() {
-                let _ = tray.set_title(Some("No symbols".to_string()));
-                let _ = tray.set_tooltip(Some("请在设置中添加品类".to_string()));
-                if let Some(icon) = pending_icon.clone() {
-                    let _ = tray.set_icon(Some(icon));
+            // 定期检查应用数据目录是否可写，状态变化时记录日志，失败期间在 tooltip 中提示设置无法保存。
+            if now >= next_data_dir_check {
+                next_data_dir_check = now + Duration::from_secs(DATA_DIR_CHECK_SECONDS);
+                let result = probe_data_dir_writable(&app).err();
+                match (&data_dir_error, &result) {
+                    (None, Some(err)) => {
+                        log_line(&format!("[xau-tray] app data dir not writable: {err}"))
+                    }
+                    (Some(_), None) => log_line("[xau-tray] app data dir writable again"),
+                    _ => {}
                 }
-                tokio::time::sleep(Duration::from_secs(1)).await;
-                continue;
+                data_dir_error = result;
             }
 
-            if rotate_index >= settings.symbols.len() {
-                rotate_index = 0;
+            // 网络恢复：退避期间窗口重新获得焦点，或连通性探测由失败转为成功时，
+            // 清零退避并立即刷新，缩短睡眠唤醒、重连 Wi-Fi 后的恢复时间。
+            let focus_hint = app
+                .state::<AppState>()
+                .network_hint
+                .swap(false, Ordering::SeqCst);
+            let mut network_regained = focus_hint && error_backoff_seconds > 0;
+            let network_down = last_error
+                .as_ref()
+                .is_some_and(|err| err.kind == FetchErrorKind::Network);
+            if error_backoff_seconds > 0 && network_down {
+                if now >= next_probe {
+                    next_probe = now + Duration::from_secs(CONNECTIVITY_PROBE_SECONDS);
+                    let reachable = probe_connectivity().await;
+                    network_regained |= reachable && probe_failed;
+                    probe_failed = !reachable;
+                }
+            } else {
+                probe_failed = false;
+            }
+            if network_regained {
+                log_line("[xau-tray] network regained, retry now");
+                error_backoff_seconds = 0;
+                probe_failed = false;
+                next_refresh = Instant::now();
             }
 
             // 到达刷新时间：请求行情并更新缓存与显示。
-            if now >= next_refresh {
+            if Instant::now() >= next_refresh {
+                let fetch_started = Instant::now();
                 let now = chrono::Local::now();
                 log_line(&format!(
                     "[xau-tray] request tick: {}",
                     now.format("%Y-%m-%d %H:%M:%S")
                 ));
                 let mut success = 0;
-                let tokens = parse_tokens(&settings.token);
+                // 因跳变检测被暂缓采用的品类数，与成功分开统计。
+                let mut rejected: u64 = 0;
+                let mut tokens = parse_tokens(&settings.token);
+                // 模拟数据模式不需要真实 token。
+                if settings.mock_mode {
+                    log_line("[xau-tray] mock mode: using synthetic quotes");
+                    if tokens.is_empty() {
+                        tokens.push("mock".to_string());
+                    }
+                }
                 // token 为空时直接提示，不发请求。
                 if tokens.is_empty() {
-                    let _ = tray.set_title(Some("设置 Token".to_string()));
-                    let _ = tray.set_tooltip(Some("请先在设置中填写 Alltick Token".to_string()));
-                    if let Some(icon) = pending_icon.clone() {
-                        let _ = tray.set_icon(Some(icon));
-                    }
+                    view.set_title(tr(locale, "set_token").to_string());
+                    view.set_tooltip(tr(locale, "set_token_tooltip").to_string());
+                    view.set_icon(IconKind::Pending);
                 } else {
                     if token_index >= tokens.len() {
                         token_index = 0;
                     }
-                    // 构造请求 code 列表，保持与设置一致的顺序。
-                    let codes: Vec<String> =
-                        settings.symbols.iter().map(|symbol| symbol.code.clone()).collect();
-                    let mut attempt = 0;
-                    let mut cursor = token_index;
+                    // 构造请求 code 列表，默认保持与设置一致的顺序；
+                    // 开启轮换顺序时每次请求的起始位置后移一位，避免固定的品类总排在末尾被限流。
+                    let mut codes = request_codes(&settings.symbols);
+                    if settings.rotate_request_order && !codes.is_empty() {
+                        let shift = request_offset % codes.len();
+                        codes.rotate_left(shift);
+                        request_offset = request_offset.wrapping_add(1);
+                    }
                     let mut last_attempt_error: Option<FetchError> = None;
-                    let mut map: Option<HashMap<String, (f64, u64, f64)>> = None;
+                    let mut map: Option<HashMap<String, Quote>> = None;
 
-                    // 逐个 token 轮换尝试，直到成功或全部失败。
-                    while attempt < tokens.len() {
-                        match fetch_batch_quotes(
-                            &tokens[cursor],
-                            &codes,
-                            settings.api_type,
-                            settings.use_system_proxy,
-                        )
-                        .await
+                    // 优先使用上次成功的 token，跳过冷却中的 token，直到成功或全部失败。
+                    // 开启轻量请求且显示配置不依赖 K 线数据时，改用只含最新价的 tick 接口。
+                    let lightweight = settings.lightweight_fetch
+                        && alltick_extras_enabled(&settings)
+                        && !needs_kline_data(&settings);
+                    let order = token_attempt_order(&tokens, token_index, &token_failures);
+                    for cursor in order {
+                        let attempt_started = Instant::now();
+                        let result = if lightweight {
+                            fetch_batch_ticks(
+                                &tokens[cursor],
+                                &codes,
+                                settings.api_type,
+                                settings.use_system_proxy,
+                                &settings.endpoints,
+                            )
+                            .await
+                        } else {
+                            fetch_batch_quotes_adaptive(
+                                &tokens[cursor],
+                                &codes,
+                                &settings,
+                                &mut batch_limit,
+                            )
+                            .await
+                        };
                         {
-                            Ok(payload) => {
+                            let mut metrics = lock_or_recover(&metrics_handle);
+                            metrics.requests_total += 1;
+                            metrics.last_latency_ms = attempt_started.elapsed().as_millis() as u64;
+                            if result.is_err() {
+                                metrics.failures_total += 1;
+                            }
+                        }
+                        match result {
+                            Ok((payload, meta)) => {
                                 map = Some(payload);
+                                last_fetch_meta = Some(meta);
                                 token_index = cursor;
+                                token_failures.remove(&tokens[cursor]);
                                 break;
                             }
                             Err(err) => {
+                                // 只有业务错误（如 token 过期）才记入该 token 的冷却，网络错误与 token 无关。
+                                if err.kind == FetchErrorKind::Business {
+                                    token_failures.insert(tokens[cursor].clone(), Instant::now());
+                                }
                                 last_attempt_error = Some(err);
-                                cursor = (cursor + 1) % tokens.len();
-                                attempt += 1;
                             }
                         }
                     }
@@ -821,91 +4972,730 @@ fn start_polling(tray: tauri::tray::TrayIcon, settings_handle: Arc<Mutex<QuoteSe
                     if let Some(map) = map {
                         // 成功时清空错误状态并写入缓存。
                         last_error = None;
+                        last_success_at = Some(chrono::Local::now());
+                        consecutive_successes += 1;
+                        consecutive_failures = 0;
                         error_backoff_seconds = 0;
+                        business_error_streak = 0;
+                        business_error_msg = None;
+                        business_error_alerted = false;
+                        blend_partial.clear();
+                        // 统计各请求代码连续无数据的次数，有数据时清零。
+                        for code in &codes {
+                            if map.contains_key(code) {
+                                empty_counts.remove(code);
+                            } else {
+                                *empty_counts.entry(code.clone()).or_insert(0) += 1;
+                            }
+                        }
                         for symbol in &settings.symbols {
-                            if let Some((price, _ts, open)) = map.get(&symbol.code) {
-                                let trend = if price > open {
-                                    "▲"
-                                } else if price < open {
-                                    "▼"
-                                } else {
-                                    "—"
-                                };
-                                last_prices.insert(symbol.code.clone(), *price);
+                            // 混合品类由成分行情求均值，缺少部分成分时标记为部分数据。
+                            // 组合指数按权重合成，缺少部分成分时同样标记为部分数据。
+                            let combined = if symbol.code == PORTFOLIO_CODE {
+                                Some(portfolio_quote(&settings.portfolio, &map))
+                            } else {
+                                symbol.blend.as_ref().map(|parts| blend_quote(parts, &map))
+                            };
+                            let quote = match combined {
+                                Some(result) => result.map(|(quote, partial)| {
+                                    if partial {
+                                        blend_partial.insert(symbol.code.clone());
+                                    }
+                                    quote
+                                }),
+                                None => map.get(&symbol.code).copied(),
+                            };
+                            // 跳变检测：可疑价格暂不采用，保留旧价格，单独计数，不算作成功。
+                            match quote {
+                                Some(new)
+                                    if !accept_price(
+                                        &mut suspect_prices,
+                                        &symbol.code,
+                                        last_prices.get(&symbol.code).copied(),
+                                        new.price,
+                                        settings.max_jump_percent,
+                                    ) =>
+                                {
+                                    rejected += 1;
+                                    lock_or_recover(&metrics_handle).rejected_prices_total += 1;
+                                    continue;
+                                }
+                                Some(_) => {}
+                                None => {
+                                    suspect_prices.remove(&symbol.code);
+                                }
+                            }
+                            if let Some(quote) = quote {
+                                // 设置了动量窗口时与 N 次刷新前的价格比较，历史不足时仍与开盘价比较。
+                                let history = price_history.entry(symbol.code.clone()).or_default();
+                                let momentum = momentum_reference(
+                                    history,
+                                    quote.price,
+                                    settings.momentum_window,
+                                );
+                                let trend = quote_trend(
+                                    &settings,
+                                    symbol,
+                                    &quote,
+                                    momentum,
+                                    trends.get(&symbol.code),
+                                );
+                                last_prices.insert(symbol.code.clone(), quote.price);
+                                last_opens.insert(symbol.code.clone(), quote.open);
+                                match quote.volume {
+                                    Some(volume) => {
+                                        last_volumes.insert(symbol.code.clone(), volume);
+                                    }
+                                    None => {
+                                        last_volumes.remove(&symbol.code);
+                                    }
+                                }
                                 trends.insert(symbol.code.clone(), trend.to_string());
+                                icon_kinds.insert(symbol.code.clone(), IconKind::from_trend(trend));
+                                icon_misses.remove(&symbol.code);
                                 success += 1;
                             } else {
                                 trends.insert(symbol.code.clone(), "—".to_string());
+                                mark_icon_missing(
+                                    &mut icon_kinds,
+                                    &mut icon_misses,
+                                    &symbol.code,
+                                    settings.hold_last_icon,
+                                );
+                            }
+                        }
+
+                        // 锚点价格只在周期切换时拉取，复用当前可用的 token。
+                        if let Some(anchor) = settings.anchor {
+                            if Instant::now() >= next_anchor_check
+                                && anchor_refresh_due(anchor, cached_anchor, &anchor_prices, &codes)
+                            {
+                                next_anchor_check =
+                                    Instant::now() + Duration::from_secs(ANCHOR_RECHECK_SECONDS);
+                                match fetch_batch_quotes(
+                                    &tokens[token_index],
+                                    &codes,
+                                    &settings,
+                                    anchor.kline_type(),
+                                    0,
+                                )
+                                .await
+                                {
+                                    Ok((anchors, _)) => {
+                                        if cached_anchor != Some(anchor) {
+                                            anchor_prices.clear();
+                                        }
+                                        cached_anchor = Some(anchor);
+                                        for (code, quote) in anchors {
+                                            anchor_prices
+                                                .insert(code, (quote.open, quote.timestamp));
+                                        }
+                                    }
+                                    Err(err) => {
+                                        log_line(&format!(
+                                            "[xau-tray] anchor request failed: {}",
+                                            err.detail
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+
+                        // 昨收每天只在日线切换后拉取一次，周末/节假日期间按复查间隔重试。
+                        if settings.show_prev_close
+                            && alltick_extras_enabled(&settings)
+                            && Instant::now() >= next_prev_close_check
+                            && anchor_refresh_due(
+                                ChangeAnchor::Day,
+                                Some(ChangeAnchor::Day),
+                                &prev_closes,
+                                &codes,
+                            )
+                        {
+                            next_prev_close_check =
+                                Instant::now() + Duration::from_secs(ANCHOR_RECHECK_SECONDS);
+                            match fetch_previous_closes(
+                                &tokens[token_index],
+                                &codes,
+                                settings.api_type,
+                                settings.use_system_proxy,
+                                &settings.endpoints,
+                            )
+                            .await
+                            {
+                                Ok(closes) => prev_closes = closes,
+                                Err(err) => {
+                                    log_line(&format!(
+                                        "[xau-tray] previous close request failed: {}",
+                                        err.detail
+                                    ));
+                                }
+                            }
+                        }
+
+                        // VWAP 只为当前展示品类定期拉取日内 K 线，混合品类不支持。
+                        if settings.show_vwap && alltick_extras_enabled(&settings) {
+                            if let Some(symbol) = pick_display_symbol(&settings, rotate_index)
+                                .filter(|symbol| symbol.blend.is_none())
+                            {
+                                let state = vwap_states.entry(symbol.code.clone()).or_default();
+                                if state.refresh_due() {
+                                    match fetch_candles(
+                                        &tokens[token_index],
+                                        &symbol.code,
+                                        settings.api_type,
+                                        settings.use_system_proxy,
+                                        &settings.endpoints,
+                                        KLINE_FIVE_MINUTES,
+                                        VWAP_CANDLE_COUNT,
+                                    )
+                                    .await
+                                    {
+                                        Ok(candles) => state.update(&candles),
+                                        Err(err) => {
+                                            state.updated_at = Some(Instant::now());
+                                            log_line(&format!(
+                                                "[xau-tray] vwap request failed: {}",
+                                                err.detail
+                                            ));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
+                        // 均线只为当前展示品类随批量刷新拉取最近 N 根 1 分钟 K 线，混合品类不支持。
+                        let show_ma = settings
+                            .show_ma
+                            .filter(|_| alltick_extras_enabled(&settings));
+                        if let Some(period) = show_ma {
+                            if let Some(symbol) = pick_display_symbol(&settings, rotate_index)
+                                .filter(|symbol| symbol.blend.is_none())
+                            {
+                                match fetch_candles(
+                                    &tokens[token_index],
+                                    &symbol.code,
+                                    settings.api_type,
+                                    settings.use_system_proxy,
+                                    &settings.endpoints,
+                                    KLINE_MINUTE,
+                                    period,
+                                )
+                                .await
+                                {
+                                    Ok(candles) => {
+                                        let ma = simple_moving_average(&candles, period);
+                                        ma_values.insert(symbol.code.clone(), (period, ma));
+                                    }
+                                    Err(err) => {
+                                        log_line(&format!(
+                                            "[xau-tray] ma request failed: {}",
+                                            err.detail
+                                        ));
+                                    }
+                                }
                             }
                         }
                     } else {
                         // 全部 token 失败才进入退避模式。
                         last_error = last_attempt_error;
-                        token_index = 0;
-                        error_backoff_seconds = if error_backoff_seconds == 0 {
-                            (base_refresh_seconds * 3).max(base_refresh_seconds)
-                        } else {
-                            (error_backoff_seconds * 2).min(ERROR_BACKOFF_MAX_SECONDS)
-                        };
-                        if error_backoff_seconds < base_refresh_seconds {
-                            error_backoff_seconds = base_refresh_seconds;
+                        consecutive_failures += 1;
+                        consecutive_successes = 0;
+                        // 刚进入全部失败状态时通知前端一次，退避期间不重复发送。
+                        if consecutive_failures == 1 {
+                            if let Some(err) = last_error.as_ref() {
+                                let message = tr(locale, "tokens_exhausted")
+                                    .replace("{msg}", err.msg.as_deref().unwrap_or(&err.detail));
+                                let _ = app.emit(
+                                    "tokens-exhausted",
+                                    AlertPayload {
+                                        message,
+                                        error: Some(err.clone()),
+                                    },
+                                );
+                            }
                         }
+                        // 只有业务错误（如 token 失效）才累计提醒计数，网络错误会打断连续计数。
+                        match last_error.as_ref() {
+                            Some(err) if err.kind == FetchErrorKind::Business => {
+                                if business_error_msg == err.msg {
+                                    business_error_streak += 1;
+                                } else {
+                                    business_error_streak = 1;
+                                    business_error_msg = err.msg.clone();
+                                    business_error_alerted = false;
+                                }
+                                if business_error_streak >= BUSINESS_ERROR_ALERT_THRESHOLD
+                                    && !business_error_alerted
+                                {
+                                    business_error_alerted = true;
+                                    alert_business_error(&app, err, locale);
+                                }
+                            }
+                            _ => {
+                                business_error_streak = 0;
+                            }
+                        }
+                        error_backoff_seconds = next_error_backoff(
+                            error_backoff_seconds,
+                            base_refresh_seconds,
+                            settings.backoff_multiplier,
+                            settings.max_backoff_seconds,
+                        );
                         for symbol in &settings.symbols {
                             trends.insert(symbol.code.clone(), "—".to_string());
+                            mark_icon_missing(
+                                &mut icon_kinds,
+                                &mut icon_misses,
+                                &symbol.code,
+                                settings.hold_last_icon,
+                            );
                         }
                     }
 
                     // tooltip 优先展示错误信息，再展示各品类行情。
-                    let mut tooltip_lines: Vec<String> = Vec::new();
+                    let mut error_lines: Vec<String> = Vec::new();
                     if let Some(err) = last_error.as_ref() {
-                        tooltip_lines.extend(err.tooltip_lines());
+                        error_lines.extend(err.tooltip_lines(locale));
                     }
-                    tooltip_lines.extend(settings.symbols.iter().map(|symbol| {
-                        let trend = trends.get(&symbol.code).map(|s| s.as_str());
-                        let price = last_prices.get(&symbol.code).copied();
-                        format_price_line(symbol, price, trend)
-                    }));
-                    let _ = tray.set_tooltip(Some(tooltip_lines.join("\n")));
-
-                    if success == 0 {
-                        // 全部失败：标题加红点或追加 * 提示非最新。
-                        if let Some(err) = last_error.as_ref() {
-                            let _ = tray.set_title(Some(error_title(&last_title)));
-                        } else if !last_title.is_empty() && !last_title.ends_with('*') {
-                            last_title.push('*');
-                            let _ = tray.set_title(Some(last_title.clone()));
-                        }
-                        if let Some(icon) = pending_icon.clone() {
-                            let _ = tray.set_icon(Some(icon));
-                        }
-                    } else if let Some(symbol) = pick_display_symbol(&settings, rotate_index) {
-                        // 只要有成功数据，就更新标题与图标。
+                    if let Some(err) = data_dir_error.as_ref() {
+                        error_lines.push(tr(locale, "data_dir_unwritable").replace("{err}", err));
+                    }
+                    let displayed_code =
+                        pick_display_symbol(&settings, rotate_index).map(|s| s.code.clone());
+                    let mut symbol_lines: Vec<Vec<String>> = Vec::new();
+                    for symbol in &settings.symbols {
+                        let mut tooltip_lines: Vec<String> = Vec::new();
                         let trend = trends.get(&symbol.code).map(|s| s.as_str());
                         let price = last_prices.get(&symbol.code).copied();
-                        last_title = format_title(symbol, price, trend);
-                        let title = if last_error.is_some() {
-                            error_title(&last_title)
-                        } else {
-                            last_title.clone()
-                        };
-                        let _ = tray.set_title(Some(title));
-                        let icon = match trend {
-                            Some("▲") => up_icon.clone(),
-                            Some("▼") => down_icon.clone(),
-                            _ => pending_icon.clone(),
-                        };
-                        if let Some(icon) = icon {
-                            let _ = tray.set_icon(Some(icon));
+                        let mut line = format_price_line(&settings, symbol, price, trend);
+                        // 开启绝对涨跌额时，在价格后追加相对开盘价的变化，如 "+18.50"。
+                        if settings.show_abs_change {
+                            let open = last_opens.get(&symbol.code);
+                            let shown = price.and_then(|price| display_value(symbol, price));
+                            let shown_open = open.and_then(|open| display_value(symbol, *open));
+                            if let (Some(price), Some(open)) = (shown, shown_open) {
+                                let decimals = price_decimals(symbol);
+                                line.push_str(&format!(" {:+.*}", decimals, price - open));
+                            }
+                        }
+                        if blend_partial.contains(&symbol.code) {
+                            line.push_str(&format!(" ({})", tr(locale, "blend_partial")));
+                        }
+                        if suspect_prices.contains_key(&symbol.code) {
+                            line.push_str(" ?");
+                        }
+                        if settings.show_volume {
+                            if let Some(volume) = last_volumes.get(&symbol.code) {
+                                let label = tr(locale, "volume");
+                                line.push_str(&format!(" {label} {}", format_compact(*volume)));
+                            }
+                        }
+                        if market_closed(symbol) {
+                            line.push_str(&format!(" {}", tr(locale, "market_closed")));
+                        }
+                        tooltip_lines.push(line);
+                        // 长期无数据且代码形态像另一类接口时，提示用户切换接口类型。
+                        if let Some(hint) = empty_data_hint(
+                            symbol,
+                            settings.api_type,
+                            empty_counts.get(&symbol.code).copied().unwrap_or(0),
+                            locale,
+                        ) {
+                            tooltip_lines.push(hint);
+                        }
+                        // 启用锚点时，在每个品类下追加一行锚点涨跌幅。
+                        if let Some(anchor) = settings.anchor {
+                            let anchor_price = if cached_anchor == Some(anchor) {
+                                anchor_prices.get(&symbol.code).map(|(open, _)| *open)
+                            } else {
+                                None
+                            };
+                            tooltip_lines.push(format_anchor_line(
+                                anchor,
+                                price,
+                                anchor_price,
+                                locale,
+                            ));
+                        }
+                        // 开启昨收对比时，追加与上一交易日收盘价的涨跌幅。
+                        if settings.show_prev_close {
+                            let prev_close = prev_closes.get(&symbol.code).map(|(close, _)| *close);
+                            tooltip_lines.push(format_prev_close_line(
+                                &settings, symbol, price, prev_close, locale,
+                            ));
+                        }
+                        // 设置了入场价时，追加相对入场价的涨跌幅。
+                        if let Some(entry) = symbol.entry_price {
+                            tooltip_lines
+                                .push(format_entry_line(&settings, symbol, price, entry, locale));
+                        }
+                        // 开启 VWAP 时，仅在当前展示品类下追加。
+                        if settings.show_vwap && displayed_code.as_ref() == Some(&symbol.code) {
+                            let vwap = vwap_states.get(&symbol.code).and_then(|s| s.value);
+                            if let Some(vwap) = vwap {
+                                tooltip_lines.push(format!(
+                                    "VWAP {}",
+                                    format_price(&settings, symbol, vwap)
+                                ));
+                            }
+                        }
+                        // 开启均线时，仅在当前展示品类下追加，如 "MA5 2330.12"。
+                        if let Some(period) = settings.show_ma {
+                            if displayed_code.as_ref() == Some(&symbol.code) {
+                                let ma = ma_values
+                                    .get(&symbol.code)
+                                    .filter(|(cached, _)| *cached == period)
+                                    .and_then(|(_, ma)| *ma);
+                                tooltip_lines.push(match ma {
+                                    Some(ma) => format!(
+                                        "MA{period} {}",
+                                        format_price(&settings, symbol, ma)
+                                    ),
+                                    None => format!("MA{period} --"),
+                                });
+                            }
+                        }
+                        symbol_lines.push(tooltip_lines);
+                    }
+                    // 开启健康统计时，在末尾追加连续成功/失败次数。
+                    let mut footer_lines: Vec<String> = Vec::new();
+                    if settings.show_health {
+                        footer_lines.push(
+                            tr(locale, "health")
+                                .replace("{successes}", &consecutive_successes.to_string())
+                                .replace("{failures}", &consecutive_failures.to_string()),
+                        );
+                    }
+                    // 开启接口信息时，追加最近一次成功请求的 ret、条数与耗时。
+                    if settings.show_api_ret {
+                        if let Some(meta) = last_fetch_meta {
+                            footer_lines.push(format!(
+                                "ret={} items={} ({}ms)",
+                                meta.ret, meta.items, meta.elapsed_ms
+                            ));
+                        }
+                    }
+                    // 末尾显示最近一次成功刷新的时间，出错时可据此判断数据有多旧。
+                    if let Some(time) = last_success_at {
+                        let time = time.format(settings.time_format.pattern()).to_string();
+                        footer_lines.push(tr(locale, "updated_at").replace("{time}", &time));
+                    }
+                    // 超出最大行数时保留错误信息与当前展示品类，其余品类折叠。
+                    let displayed = displayed_code
+                        .and_then(|code| settings.symbols.iter().position(|s| s.code == code));
+                    let tooltip_lines = fit_tooltip_lines(
+                        error_lines,
+                        symbol_lines,
+                        displayed,
+                        footer_lines,
+                        settings.max_tooltip_lines,
+                        locale,
+                    );
+                    let tooltip = tooltip_lines.join("\n");
+                    // 保存最新的 tooltip 文本，供“复制全部”菜单使用。
+                    lock_or_recover(&metrics_handle).tooltip = tooltip.clone();
+                    view.set_tooltip(tooltip);
+
+                    if success == 0 && rejected == 0 {
+                        // 全部失败：标题加错误提示符或追加非最新提示符（可关闭，仅保留图标与 tooltip 提示）。
+                        // 仅图标模式下出错时清空标题，只用错误图标提示，节省菜单栏空间。
+                        // 展示品类休市时不加错误/非最新提示，保留最后的标题。
+                        // 非全天候品类（如股票）盘后数据不更新属正常，不追加非最新提示符。
+                        let stale = settings.stale_indicator.as_str();
+                        let display_symbol = pick_display_symbol(&settings, rotate_index);
+                        let closed = display_symbol.is_some_and(market_closed);
+                        let always_open = display_symbol
+                            .is_none_or(|symbol| symbol_always_open(symbol, settings.api_type));
+                        let icon_only = last_error.is_some() && settings.error_icon_only && !closed;
+                        if icon_only {
+                            view.set_title(String::new());
+                        } else if closed {
+                            view.set_title(last_title.clone());
+                        } else if let Some(err) = last_error.as_ref() {
+                            view.set_title(error_title(&settings.error_indicator, &last_title));
+                        } else if settings.stale_marker_in_title
+                            && always_open
+                            && !last_title.is_empty()
+                            && !stale.is_empty()
+                            && !last_title.ends_with(stale)
+                        {
+                            last_title.push_str(stale);
+                            view.set_title(last_title.clone());
+                        }
+                        // 保持图标时，短暂失败期间沿用上一次的方向图标。
+                        if icon_only {
+                            view.set_icon(IconKind::Error);
+                        } else if !settings.hold_last_icon
+                            || consecutive_failures >= HOLD_ICON_MAX_MISSES
+                        {
+                            view.set_icon(IconKind::Pending);
+                        }
+                    } else {
+                        // 最大波动模式：每次刷新后重新选出涨跌幅最大的品类。
+                        if settings.display_mode == DisplayMode::TopMover {
+                            if let Some(index) =
+                                top_mover_index(&settings.symbols, &last_prices, &last_opens)
+                            {
+                                rotate_index = index;
+                            }
+                        }
+                        // 最新成交价模式：只为当前展示品类额外请求一次 tick。
+                        tick_prices.clear();
+                        if settings.price_source == PriceSource::LastTick
+                            && alltick_extras_enabled(&settings)
+                        {
+                            if let Some(symbol) = pick_display_symbol(&settings, rotate_index) {
+                                match fetch_last_tick(
+                                    &tokens[token_index],
+                                    &symbol.code,
+                                    settings.api_type,
+                                    settings.use_system_proxy,
+                                    &settings.endpoints,
+                                )
+                                .await
+                                {
+                                    Ok(price) => {
+                                        tick_prices.insert(symbol.code.clone(), price);
+                                    }
+                                    Err(err) => {
+                                        log_line(&format!(
+                                            "[xau-tray] tick request failed: {}",
+                                            err.detail
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                        // 每次成功刷新后推进一次各品类的标题平滑价，标题渲染只读取缓存。
+                        for symbol in &settings.symbols {
+                            let raw = tick_prices
+                                .get(&symbol.code)
+                                .or_else(|| last_prices.get(&symbol.code));
+                            if let Some(raw) = raw {
+                                advance_smoothed_price(
+                                    &mut smoothed_prices,
+                                    &symbol.code,
+                                    *raw,
+                                    settings.smoothing_alpha,
+                                );
+                            }
+                        }
+                        if let Some(symbol) = pick_display_symbol(&settings, rotate_index) {
+                            // 只要有成功数据，就更新标题与图标。
+                            let price = tick_prices
+                                .get(&symbol.code)
+                                .or_else(|| last_prices.get(&symbol.code))
+                                .map(|raw| {
+                                    title_price(
+                                        &smoothed_prices,
+                                        &mut held_title_prices,
+                                        &settings,
+                                        symbol,
+                                        *raw,
+                                    )
+                                });
+                            last_title = render_display_symbol(
+                                &mut view,
+                                &settings,
+                                symbol,
+                                price,
+                                trends.get(&symbol.code).map(String::as_str),
+                                icon_kinds.get(&symbol.code).copied(),
+                                last_error.as_ref(),
+                            );
                         }
                     }
                 }
-                // 根据是否退避来决定下一次刷新间隔。
+                // 根据是否退避来决定下一次刷新间隔；推送连接正常时批量请求降频。
                 let refresh_seconds = if error_backoff_seconds > 0 {
-                    error_backoff_seconds.min(ERROR_BACKOFF_MAX_SECONDS)
+                    error_backoff_seconds.min(settings.max_backoff_seconds)
+                } else if stream_connected {
+                    base_refresh_seconds.max(STREAM_KLINE_REFRESH_SECONDS)
                 } else {
                     base_refresh_seconds
                 };
-                next_refresh = Instant::now() + Duration::from_secs(refresh_seconds);
+                // 请求在循环内串行等待，不会重叠；耗时超过间隔时跳过错过的刷新点，而不是立即补发。
+                let finished = Instant::now();
+                log_line(&format!(
+                    "[xau-tray] fetch took {}ms",
+                    finished.duration_since(fetch_started).as_millis()
+                ));
+                {
+                    let mut metrics = lock_or_recover(&metrics_handle);
+                    metrics.backoff_seconds = error_backoff_seconds;
+                    metrics.last_prices = last_prices.clone();
+                    metrics.trends = trends.clone();
+                    // 更新会话统计的最高/最低价，首次出现的品类以当前价格作为起点。
+                    if metrics.session_started_at.is_none() {
+                        metrics.session_started_at = Some(chrono::Local::now().to_rfc3339());
+                    }
+                    for (code, price) in &last_prices {
+                        let range = metrics
+                            .session_ranges
+                            .entry(code.clone())
+                            .or_insert((*price, *price, *price));
+                        range.1 = range.1.max(*price);
+                        range.2 = range.2.min(*price);
+                    }
+                    metrics.last_refresh_at = Some(chrono::Local::now().to_rfc3339());
+                    metrics.last_error = last_error.clone();
+                    metrics.consecutive_successes = consecutive_successes;
+                    metrics.consecutive_failures = consecutive_failures;
+                }
+                let (next, skipped) = schedule_next_refresh(
+                    fetch_started,
+                    Duration::from_secs(refresh_seconds),
+                    finished,
+                );
+                if skipped > 0 {
+                    log_line(&format!(
+                        "[xau-tray] skipped {skipped} refresh tick(s): fetch was slow"
+                    ));
+                }
+                // 对齐时钟：正常刷新时改为在下一个整倍数时刻刷新（如每分钟的 :00/:10/:20 秒），退避期间仍按退避间隔。
+                next_refresh = if settings.align_to_clock && error_backoff_seconds == 0 {
+                    Instant::now() + delay_to_clock_boundary(refresh_seconds)
+                } else {
+                    next
+                };
+                // 批量刷新刚拿到全部品类的数据，焦点刷新从此刻重新计时。
+                if let Some(focus_seconds) = settings.focus_refresh_seconds {
+                    next_focus_refresh = Instant::now() + Duration::from_secs(focus_seconds);
+                }
+            }
+
+            // 焦点快速刷新：两次批量刷新之间，只为当前展示品类单独请求，出错退避期间暂停。
+            if let Some(focus_seconds) = settings.focus_refresh_seconds {
+                let tokens = parse_tokens(&settings.token);
+                let focus_symbol = pick_display_symbol(&settings, rotate_index)
+                    .filter(|symbol| symbol.blend.is_none());
+                if Instant::now() >= next_focus_refresh
+                    && last_error.is_none()
+                    && token_index < tokens.len()
+                {
+                    next_focus_refresh = Instant::now() + Duration::from_secs(focus_seconds);
+                    if let Some(symbol) = focus_symbol {
+                        let token = &tokens[token_index];
+                        // 焦点刷新拿到的价格同样经过跳变检测，可疑价格不写入缓存。
+                        let mut check_jump = |last: Option<f64>, price: f64| {
+                            let accepted = accept_price(
+                                &mut suspect_prices,
+                                &symbol.code,
+                                last,
+                                price,
+                                settings.max_jump_percent,
+                            );
+                            if !accepted {
+                                lock_or_recover(&metrics_handle).rejected_prices_total += 1;
+                            }
+                            accepted
+                        };
+                        let updated = if settings.price_source == PriceSource::LastTick
+                            && alltick_extras_enabled(&settings)
+                        {
+                            fetch_last_tick(
+                                token,
+                                &symbol.code,
+                                settings.api_type,
+                                settings.use_system_proxy,
+                                &settings.endpoints,
+                            )
+                            .await
+                            .map(|price| {
+                                let last = tick_prices
+                                    .get(&symbol.code)
+                                    .or_else(|| last_prices.get(&symbol.code))
+                                    .copied();
+                                if check_jump(last, price) {
+                                    tick_prices.insert(symbol.code.clone(), price);
+                                }
+                            })
+                        } else {
+                            fetch_batch_quotes(
+                                token,
+                                std::slice::from_ref(&symbol.code),
+                                &settings,
+                                KLINE_MINUTE,
+                                settings.baseline_offset,
+                            )
+                            .await
+                            .map(|(map, _)| {
+                                let Some(quote) = map.get(&symbol.code) else {
+                                    return;
+                                };
+                                let last = last_prices.get(&symbol.code).copied();
+                                if !check_jump(last, quote.price) {
+                                    return;
+                                }
+                                // 与批量刷新相同的趋势计算，但不记入动量历史。
+                                let momentum = peek_momentum_reference(
+                                    price_history.get(&symbol.code),
+                                    settings.momentum_window,
+                                );
+                                let trend = quote_trend(
+                                    &settings,
+                                    symbol,
+                                    quote,
+                                    momentum,
+                                    trends.get(&symbol.code),
+                                );
+                                last_prices.insert(symbol.code.clone(), quote.price);
+                                last_opens.insert(symbol.code.clone(), quote.open);
+                                match quote.volume {
+                                    Some(volume) => {
+                                        last_volumes.insert(symbol.code.clone(), volume);
+                                    }
+                                    None => {
+                                        last_volumes.remove(&symbol.code);
+                                    }
+                                }
+                                trends.insert(symbol.code.clone(), trend.to_string());
+                                icon_kinds.insert(symbol.code.clone(), IconKind::from_trend(trend));
+                            })
+                        };
+                        match updated {
+                            Ok(()) => {
+                                let raw = tick_prices
+                                    .get(&symbol.code)
+                                    .or_else(|| last_prices.get(&symbol.code))
+                                    .copied();
+                                if let Some(raw) = raw {
+                                    advance_smoothed_price(
+                                        &mut smoothed_prices,
+                                        &symbol.code,
+                                        raw,
+                                        settings.smoothing_alpha,
+                                    );
+                                }
+                                let price = raw.map(|raw| {
+                                    title_price(
+                                        &smoothed_prices,
+                                        &mut held_title_prices,
+                                        &settings,
+                                        symbol,
+                                        raw,
+                                    )
+                                });
+                                last_title = render_display_symbol(
+                                    &mut view,
+                                    &settings,
+                                    symbol,
+                                    price,
+                                    trends.get(&symbol.code).map(String::as_str),
+                                    icon_kinds.get(&symbol.code).copied(),
+                                    last_error.as_ref(),
+                                );
+                            }
+                            Err(err) => {
+                                log_line(&format!(
+                                    "[xau-tray] focus request failed: {}",
+                                    err.detail
+                                ));
+                            }
+                        }
+                    }
+                }
             }
 
             // 轮播模式下到点切换展示品类，不触发网络请求。
@@ -913,23 +5703,27 @@ fn start_polling(tray: tauri::tray::TrayIcon, settings_handle: Arc<Mutex<QuoteSe
                 next_rotate = now + rotate_interval;
                 rotate_index = (rotate_index + 1) % settings.symbols.len();
                 if let Some(symbol) = pick_display_symbol(&settings, rotate_index) {
-                    let trend = trends.get(&symbol.code).map(|s| s.as_str());
-                    let price = last_prices.get(&symbol.code).copied();
-                    last_title = format_title(symbol, price, trend);
-                    let title = if last_error.is_some() {
-                        error_title(&last_title)
-                    } else {
-                        last_title.clone()
-                    };
-                    let _ = tray.set_title(Some(title));
-                    let icon = match trend {
-                        Some("▲") => up_icon.clone(),
-                        Some("▼") => down_icon.clone(),
-                        _ => pending_icon.clone(),
-                    };
-                    if let Some(icon) = icon {
-                        let _ = tray.set_icon(Some(icon));
-                    }
+                    let price = tick_prices
+                        .get(&symbol.code)
+                        .or_else(|| last_prices.get(&symbol.code))
+                        .map(|raw| {
+                            title_price(
+                                &smoothed_prices,
+                                &mut held_title_prices,
+                                &settings,
+                                symbol,
+                                *raw,
+                            )
+                        });
+                    last_title = render_display_symbol(
+                        &mut view,
+                        &settings,
+                        symbol,
+                        price,
+                        trends.get(&symbol.code).map(String::as_str),
+                        icon_kinds.get(&symbol.code).copied(),
+                        last_error.as_ref(),
+                    );
                 }
             }
 
@@ -938,17 +5732,274 @@ fn start_polling(tray: tauri::tray::TrayIcon, settings_handle: Arc<Mutex<QuoteSe
             if settings.display_mode == DisplayMode::Rotate && next_rotate < next_tick {
                 next_tick = next_rotate;
             }
+            if settings.focus_refresh_seconds.is_some() && next_focus_refresh < next_tick {
+                next_tick = next_focus_refresh;
+            }
+            // 记录当前展示品类，供左键点击复制价格使用。
+            lock_or_recover(&metrics_handle).displayed_code =
+                pick_display_symbol(&settings, rotate_index).map(|symbol| symbol.code.clone());
+            let probing = error_backoff_seconds > 0
+                && last_error
+                    .as_ref()
+                    .is_some_and(|err| err.kind == FetchErrorKind::Network);
+            if probing && next_probe < next_tick {
+                next_tick = next_probe;
+            }
+            if want_stream && stream.is_none() && stream_retry_at < next_tick {
+                next_tick = stream_retry_at;
+            }
             let sleep_for = next_tick.saturating_duration_since(Instant::now());
             let sleep_for = if sleep_for.is_zero() {
                 Duration::from_secs(1)
             } else {
                 sleep_for
             };
-            tokio::time::sleep(sleep_for).await;
+            // 推送连接存在时，收到推送也会提前醒来处理。
+            tokio::select! {
+                _ = shutdown.sleep(sleep_for) => {}
+                event = stream_receiver.recv() => woken_event = event,
+            }
         }
+    };
+    // 取消令牌触发时立即丢弃轮询任务（包括进行中的请求）。
+    tauri::async_runtime::spawn(async move {
+        cancel.run_until_cancelled(polling).await;
     });
 }
 
+// 构建托盘菜单：设置、立即刷新、自选列表子菜单（勾选当前列表）、复制全部、关于与退出。
+fn build_tray_menu(
+    app: &AppHandle,
+    settings: &QuoteSettings,
+    locale: &str,
+) -> tauri::Result<Menu<tauri::Wry>> {
+    let settings_menu = MenuItem::with_id(
+        app,
+        "settings",
+        tr(locale, "menu_settings"),
+        true,
+        Option::<&str>::None,
+    )?;
+    let refresh_menu = MenuItem::with_id(
+        app,
+        "refresh_now",
+        tr(locale, "menu_refresh_now"),
+        true,
+        Option::<&str>::None,
+    )?;
+    let watchlists_menu =
+        Submenu::with_id_and_items(app, "watchlists", tr(locale, "menu_watchlists"), true, &[])?;
+    for (index, list) in settings.watchlists.iter().enumerate() {
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("watchlist:{index}"),
+            &list.name,
+            true,
+            index == settings.active_watchlist,
+            Option::<&str>::None,
+        )?;
+        watchlists_menu.append(&item)?;
+    }
+    let copy_all_menu = MenuItem::with_id(
+        app,
+        "copy_all",
+        tr(locale, "menu_copy_all"),
+        true,
+        Option::<&str>::None,
+    )?;
+    let about_menu = MenuItem::with_id(
+        app,
+        "about",
+        tr(locale, "menu_about"),
+        true,
+        Option::<&str>::None,
+    )?;
+    let quit = MenuItem::with_id(
+        app,
+        "quit",
+        tr(locale, "menu_quit"),
+        true,
+        Option::<&str>::None,
+    )?;
+    Menu::with_items(
+        app,
+        &[
+            &settings_menu,
+            &refresh_menu,
+            &watchlists_menu,
+            &copy_all_menu,
+            &about_menu,
+            &quit,
+        ],
+    )
+}
+
+// 按当前设置重建托盘菜单，使自选列表子菜单与设置保持一致。
+fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let settings = lock_or_recover(&app.state::<AppState>().settings).clone();
+    let locale = effective_locale(&settings.locale);
+    match build_tray_menu(app, &settings, locale) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(err) => log_line(&format!("[xau-tray] rebuild tray menu failed: {err}")),
+    }
+    let _ = tray.set_show_menu_on_left_click(settings.left_click_action == ClickAction::Menu);
+}
+
+// 打开设置窗口并聚焦。
+fn show_settings_window(app: &AppHandle) {
+    if let Some(win) = app.get_webview_window("main") {
+        let _ = win.show();
+        let _ = win.set_focus();
+    }
+}
+
+// 将最新的 tooltip 文本（各品类行情与末尾的更新时间）复制到剪贴板。
+fn copy_all_prices(app: &AppHandle) {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let state = app.state::<AppState>();
+    let text = lock_or_recover(&state.metrics).tooltip.clone();
+    if text.is_empty() {
+        return;
+    }
+    if let Err(err) = app.clipboard().write_text(text) {
+        log_line(&format!("[xau-tray] copy all prices failed: {err}"));
+    }
+}
+
+// 处理托盘图标左键点击；菜单模式由系统直接弹出菜单，这里不做处理。
+fn handle_left_click(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let settings = lock_or_recover(&state.settings).clone();
+    match settings.left_click_action {
+        ClickAction::Menu => {}
+        ClickAction::Settings => show_settings_window(app),
+        ClickAction::NextSymbol => {
+            state.next_symbol.store(true, Ordering::SeqCst);
+            state.shutdown.wake();
+        }
+        ClickAction::CopyPrice => {
+            use tauri_plugin_clipboard_manager::ClipboardExt;
+
+            let text = {
+                let metrics = lock_or_recover(&state.metrics);
+                metrics.displayed_code.as_ref().and_then(|code| {
+                    let symbol = settings.symbols.iter().find(|s| &s.code == code)?;
+                    let price = metrics.last_prices.get(code)?;
+                    Some(format_price(&settings, symbol, *price))
+                })
+            };
+            let Some(text) = text else {
+                return;
+            };
+            if let Err(err) = app.clipboard().write_text(text) {
+                log_line(&format!("[xau-tray] copy price failed: {err}"));
+            }
+        }
+    }
+}
+
+// 切换到指定自选列表：symbols 换成该列表的内容并保存，轮询任务下一轮即按新列表刷新。
+fn switch_watchlist(app: &AppHandle, index: usize) -> Result<(), String> {
+    let mut next = lock_or_recover(&app.state::<AppState>().settings).clone();
+    let Some(list) = next.watchlists.get(index) else {
+        return Err(format!("watchlist {index} not found"));
+    };
+    next.symbols = list.symbols.clone();
+    next.active_watchlist = index;
+    store_settings(app, next).map(|_| ())
+}
+
+// 重启轮询任务：取消当前任务并用新的取消令牌重新启动，循环内的缓存随之清空。
+fn restart_polling(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let state = app.state::<AppState>();
+    let cancel = CancellationToken::new();
+    let previous = std::mem::replace(&mut *lock_or_recover(&state.polling), cancel.clone());
+    previous.cancel();
+    log_line("[xau-tray] restart polling");
+    start_polling(
+        app.clone(),
+        tray,
+        state.settings.clone(),
+        state.metrics.clone(),
+        state.shutdown.clone(),
+        cancel,
+    );
+}
+
+// 需要重启应用才能生效的设置是否变化：目前只有指标端口，它在启动时绑定监听，运行中无法更换。
+// 其余设置均可实时生效：品类、显示模式、间隔、推送方式等由轮询任务每轮重新读取；
+// 语言、自选列表与左键行为在保存后由 refresh_tray_menu 重建菜单；接口类型与 token 变化时自动重启轮询任务。
+// 新增只在启动时读取的设置时需同步加入此处。
+fn settings_requires_restart(old: &QuoteSettings, new: &QuoteSettings) -> bool {
+    old.metrics_port != new.metrics_port
+}
+
+// 接口类型或 token 变化时，循环内的缓存（价格、锚点、token 健康度等）不再适用，需要重启轮询。
+fn polling_restart_required(old: &QuoteSettings, new: &QuoteSettings) -> bool {
+    old.api_type != new.api_type || old.token != new.token
+}
+
+// 构建托盘菜单与托盘图标（语言在启动时确定）。
+fn build_tray(
+    app: &AppHandle,
+    settings: &QuoteSettings,
+    locale: &str,
+) -> tauri::Result<tauri::tray::TrayIcon> {
+    let menu = build_tray_menu(app, settings, locale)?;
+
+    // 构建托盘图标与交互行为。
+    TrayIconBuilder::with_id(TRAY_ID)
+        .title(tr(locale, "app_title"))
+        .tooltip(tr(locale, "initial_tooltip"))
+        .menu(&menu)
+        .show_menu_on_left_click(settings.left_click_action == ClickAction::Menu)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                handle_left_click(tray.app_handle());
+            }
+        })
+        .on_menu_event(|app, event| {
+            if event.id() == "settings" {
+                show_settings_window(app);
+            } else if event.id() == "refresh_now" {
+                log_line("[xau-tray] refresh requested");
+                request_refresh(&app.state::<AppState>());
+            } else if event.id() == "copy_all" {
+                copy_all_prices(app);
+            } else if event.id() == "about" {
+                let _ = app
+                    .opener()
+                    .open_url("https://github.com/yantaolu/xau-tray", None::<&str>);
+            } else if event.id() == "quit" {
+                request_shutdown(app);
+            } else if let Some(index) = event
+                .id()
+                .as_ref()
+                .strip_prefix("watchlist:")
+                .and_then(|index| index.parse::<usize>().ok())
+            {
+                if let Err(err) = switch_watchlist(app, index) {
+                    log_line(&format!("[xau-tray] switch watchlist failed: {err}"));
+                }
+            }
+        })
+        .build(app)
+}
+
 // 应用入口：初始化插件、托盘菜单与轮询任务。
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -962,57 +6013,526 @@ pub fn run() {
                 let _ = app.handle().set_activation_policy(tauri::ActivationPolicy::Accessory);
                 let _ = app.handle().set_dock_visibility(false);
             }
+            // 先初始化日志文件，读取设置过程中的日志也能写入。
+            init_log_file(app.handle());
             // 读取设置并注入共享状态。
             let settings = load_settings(app.handle());
+            let locale = effective_locale(&settings.locale).to_string();
+            let metrics_port = settings.metrics_port;
+            let settings_snapshot = settings.clone();
             let state = AppState {
                 settings: Arc::new(Mutex::new(settings)),
+                metrics: Arc::new(Mutex::new(PollMetrics::default())),
+                shutdown: Arc::new(ShutdownSignal::default()),
+                polling: Mutex::new(CancellationToken::new()),
+                display_lock: Mutex::new(None),
+                network_hint: AtomicBool::new(false),
+                turbo: Mutex::new(None),
+                next_symbol: AtomicBool::new(false),
+                refresh_requested: AtomicBool::new(false),
+                tray_error: Mutex::new(None),
             };
             let settings_handle = state.settings.clone();
+            let metrics_handle = state.metrics.clone();
+            let shutdown = state.shutdown.clone();
+            let cancel = lock_or_recover(&state.polling).clone();
             app.manage(state);
 
-            // 构建托盘菜单。
-            let settings_menu =
-                MenuItem::with_id(app, "settings", "设置", true, Option::<&str>::None)?;
-            let about_menu =
-                MenuItem::with_id(app, "about", "关于", true, Option::<&str>::None)?;
-            let quit = MenuItem::with_id(app, "quit", "退出", true, Option::<&str>::None)?;
-            let menu = Menu::with_items(app, &[&settings_menu, &about_menu, &quit])?;
-
-            // 构建托盘图标与交互行为。
-            let tray = TrayIconBuilder::with_id("xau-tray")
-                .title("盯价助手")
-                .tooltip("请先进行必要的设置")
-                .menu(&menu)
-                .show_menu_on_left_click(true)
-                .on_menu_event(|app, event| {
-                    if event.id() == "settings" {
-                        if let Some(win) = app.get_webview_window("main") {
-                            // 打开设置窗口并聚焦。
-                            let _ = win.show();
-                            let _ = win.set_focus();
-                        }
-                    } else if event.id() == "about" {
-                        let _ = app
-                            .opener()
-                            .open_url("https://github.com/yantaolu/xau-tray", None::<&str>);
-                    } else if event.id() == "quit" {
-                        app.exit(0);
+            // 配置了指标端口时启动本地指标服务（修改端口需重启生效）。
+            if let Some(port) = metrics_port {
+                start_metrics_server(port, metrics_handle.clone());
+            }
+
+            // 构建托盘；部分 Linux 桌面的系统托盘不稳定，失败时稍后重试一次，
+            // 仍失败则记录原因并打开设置窗口提示，应用保持可用。
+            let tray = match build_tray(app.handle(), &settings_snapshot, &locale) {
+                Ok(tray) => Some(tray),
+                Err(err) => {
+                    log_line(&format!("[xau-tray] build tray failed: {err}, retrying"));
+                    std::thread::sleep(Duration::from_millis(TRAY_RETRY_DELAY_MS));
+                    match build_tray(app.handle(), &settings_snapshot, &locale) {
+                        Ok(tray) => Some(tray),
+                        Err(err) => {
+                            log_line(&format!("[xau-tray] build tray failed again: {err}"));
+                            let state = app.state::<AppState>();
+                            *lock_or_recover(&state.tray_error) = Some(err.to_string());
+                            show_settings_window(app.handle());
+                            None
+                        }
                     }
-                })
-                .build(app)?;
+                }
+            };
+
+            // 恢复上次保存的设置窗口位置与大小。
+            restore_window_geometry(app.handle());
 
-            // 启动行情轮询任务。
-            start_polling(tray, settings_handle);
+            // 监听终止信号，与退出菜单共用收尾流程。
+            start_signal_listener(app.handle().clone());
+
+            // 启动行情轮询任务；没有托盘时无处展示行情，不启动。
+            if let Some(tray) = tray {
+                start_polling(
+                    app.handle().clone(),
+                    tray,
+                    settings_handle,
+                    metrics_handle,
+                    shutdown,
+                    cancel,
+                );
+            }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![get_settings, save_settings_command])
+        .invoke_handler(tauri::generate_handler![
+            get_settings,
+            save_settings_command,
+            debug_raw_response,
+            get_proxy_info,
+            settings_path,
+            legacy_token_path,
+            reload_settings,
+            kline_types,
+            get_app_snapshot,
+            append_snapshot_csv,
+            lock_display,
+            ping_api,
+            server_time_skew,
+            turbo_mode,
+            read_logs,
+            get_tray_error,
+            pin_symbol,
+            unpin,
+            reset_field,
+            session_stats,
+            reset_session_stats,
+            preview_quotes,
+            refresh_now
+        ])
         .on_window_event(|window, event| {
-            // 关闭窗口时改为隐藏，保持托盘运行。
-            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                api.prevent_close();
-                let _ = window.hide();
+            match event {
+                // 关闭窗口时改为隐藏，保持托盘运行。
+                tauri::WindowEvent::CloseRequested { api, .. } => {
+                    api.prevent_close();
+                    // 隐藏前记录窗口位置与大小，下次启动时恢复。
+                    if let Err(err) = save_window_geometry(window.app_handle()) {
+                        log_line(&format!("[xau-tray] save window geometry failed: {err}"));
+                    }
+                    let _ = window.hide();
+                }
+                // 窗口重新获得焦点时提示轮询任务检查网络，退避中则立即重试。
+                tauri::WindowEvent::Focused(true) => {
+                    let state = window.state::<AppState>();
+                    state.network_hint.store(true, Ordering::SeqCst);
+                    state.shutdown.wake();
+                }
+                _ => {}
             }
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 在系统临时目录下创建本测试独占的空目录。
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("xau-tray-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn concurrent_save_and_load_never_see_torn_file() {
+        let dir = temp_dir("settings");
+        let path = dir.join(SETTINGS_FILE);
+        let old = QuoteSettings {
+            token: "old-token".to_string(),
+            ..Default::default()
+        };
+        let new = QuoteSettings {
+            token: "new-token".to_string(),
+            symbols: (0..200)
+                .map(|index| SymbolItem {
+                    code: format!("CODE{index}"),
+                    label: format!("品类 {index}"),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let old_doc = serde_json::to_value(&old).unwrap();
+        let new_doc = serde_json::to_value(&new).unwrap();
+        write_settings_file(&path, &old).unwrap();
+
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let (old, new, path) = (&old, &new, &path);
+                scope.spawn(move || {
+                    for round in 0..50 {
+                        let next = if (round + writer) % 2 == 0 { new } else { old };
+                        write_settings_file(path, next).unwrap();
+                    }
+                });
+            }
+            for _ in 0..4 {
+                let (old_doc, new_doc, path) = (&old_doc, &new_doc, &path);
+                scope.spawn(move || {
+                    for _ in 0..200 {
+                        let loaded = read_settings_file(path).expect("settings file torn");
+                        let loaded = serde_json::to_value(&loaded).unwrap();
+                        assert!(&loaded == old_doc || &loaded == new_doc);
+                    }
+                });
+            }
+        });
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rotate_index_follows_symbol_changes() {
+        let codes = |list: &[&str]| list.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let previous = codes(&["A", "B", "C", "D"]);
+        // 列表未变时保持原位，越界时回到开头。
+        assert_eq!(realign_rotate_index(&previous, &previous, 2), 2);
+        assert_eq!(realign_rotate_index(&previous, &previous, 9), 0);
+        // 移除当前展示的品类：回到第一个。
+        let removed_current = codes(&["A", "B", "D"]);
+        assert_eq!(realign_rotate_index(&previous, &removed_current, 2), 0);
+        // 移除当前位置之前的品类：继续展示原品类。
+        let removed_before = codes(&["B", "C", "D"]);
+        assert_eq!(realign_rotate_index(&previous, &removed_before, 2), 1);
+        // 在当前位置之前插入品类：继续展示原品类。
+        let inserted = codes(&["A", "X", "B", "C", "D"]);
+        assert_eq!(realign_rotate_index(&previous, &inserted, 2), 3);
+        // 首次运行，之前没有列表。
+        assert_eq!(realign_rotate_index(&[], &previous, 0), 0);
+    }
+
+    // scutil --proxy 的真实输出样例：仅开启 HTTPS 代理。
+    const SCUTIL_HTTPS_ONLY: &str = "<dictionary> {
+  HTTPEnable : 0
+  HTTPSEnable : 1
+  HTTPSPort : 7890
+  HTTPSProxy : 127.0.0.1
+  ProxyAutoConfigEnable : 0
+  SOCKSEnable : 0
+}";
+
+    // 仅开启 HTTP 代理。
+    const SCUTIL_HTTP_ONLY: &str = "<dictionary> {
+  HTTPEnable : 1
+  HTTPPort : 8080
+  HTTPProxy : proxy.corp.example
+  HTTPSEnable : 0
+  ProxyAutoConfigEnable : 0
+  SOCKSEnable : 0
+}";
+
+    // HTTPS 代理地址仍保留，但已关闭。
+    const SCUTIL_HTTPS_DISABLED: &str = "<dictionary> {
+  HTTPEnable : 0
+  HTTPSEnable : 0
+  HTTPSPort : 7890
+  HTTPSProxy : 127.0.0.1
+  SOCKSEnable : 0
+}";
+
+    // 带例外列表的配置。
+    const SCUTIL_WITH_EXCEPTIONS: &str = "<dictionary> {
+  ExceptionsList : <array> {
+    0 : *.local
+    1 : 169.254/16
+  }
+  FTPPassive : 1
+  HTTPEnable : 1
+  HTTPPort : 7890
+  HTTPProxy : 127.0.0.1
+  HTTPSEnable : 1
+  HTTPSPort : 7890
+  HTTPSProxy : 127.0.0.1
+}";
+
+    #[test]
+    fn scutil_https_only() {
+        assert_eq!(
+            parse_scutil_proxy(SCUTIL_HTTPS_ONLY).as_deref(),
+            Some("http://127.0.0.1:7890")
+        );
+        assert_eq!(parse_scutil_no_proxy(SCUTIL_HTTPS_ONLY), None);
+    }
+
+    #[test]
+    fn scutil_http_only() {
+        assert_eq!(
+            parse_scutil_proxy(SCUTIL_HTTP_ONLY).as_deref(),
+            Some("http://proxy.corp.example:8080")
+        );
+    }
+
+    #[test]
+    fn scutil_https_disabled() {
+        assert_eq!(parse_scutil_proxy(SCUTIL_HTTPS_DISABLED), None);
+        assert_eq!(
+            scutil_value(SCUTIL_HTTPS_DISABLED, "HTTPSEnable").as_deref(),
+            Some("0")
+        );
+    }
+
+    #[test]
+    fn scutil_exceptions_list() {
+        assert_eq!(
+            parse_scutil_no_proxy(SCUTIL_WITH_EXCEPTIONS).as_deref(),
+            Some("*.local,169.254/16")
+        );
+        assert_eq!(
+            parse_scutil_proxy(SCUTIL_WITH_EXCEPTIONS).as_deref(),
+            Some("http://127.0.0.1:7890")
+        );
+    }
+
+    // 仅开启 SOCKS 代理。
+    const SCUTIL_SOCKS_ONLY: &str = "<dictionary> {
+  HTTPEnable : 0
+  HTTPSEnable : 0
+  ProxyAutoConfigEnable : 0
+  SOCKSEnable : 1
+  SOCKSPort : 1080
+  SOCKSProxy : 10.0.0.2
+}";
+
+    #[test]
+    fn scutil_socks_only() {
+        assert_eq!(
+            parse_scutil_proxy(SCUTIL_SOCKS_ONLY).as_deref(),
+            Some("socks5://10.0.0.2:1080")
+        );
+    }
+
+    #[test]
+    fn tooltip_folding_follows_locale() {
+        let groups: Vec<Vec<String>> = ["a", "b", "c"]
+            .iter()
+            .map(|line| vec![line.to_string()])
+            .collect();
+        let fit =
+            |locale| fit_tooltip_lines(Vec::new(), groups.clone(), Some(0), Vec::new(), 2, locale);
+        assert_eq!(fit("en"), vec!["a".to_string(), "… 2 more".to_string()]);
+        let chinese = fit("zh-CN");
+        assert_eq!(chinese.last().map(String::as_str), Some("… 其余 2 项"));
+    }
+
+    #[test]
+    fn settings_file_with_bom_parses() {
+        let dir = temp_dir("bom");
+        let path = dir.join(SETTINGS_FILE);
+        let content = "\u{FEFF}{\"token\":\"bom-token\",\"refresh_seconds\":30}";
+        fs::write(&path, content).unwrap();
+        let settings = read_settings_file(&path).expect("BOM settings should parse");
+        assert_eq!(settings.token, "bom-token");
+        assert_eq!(settings.refresh_seconds, 30);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn peek_momentum_reference_matches_recorded_reference() {
+        let mut history: VecDeque<f64> = VecDeque::new();
+        for price in [1.0, 2.0, 3.0] {
+            momentum_reference(&mut history, price, 2);
+        }
+        let peeked = peek_momentum_reference(Some(&history), 2);
+        let before = history.clone();
+        assert_eq!(peeked, momentum_reference(&mut history, 4.0, 2));
+        assert_eq!(peek_momentum_reference(Some(&before), 0), None);
+        assert_eq!(peek_momentum_reference(None, 2), None);
+    }
+
+    #[test]
+    fn proxy_url_normalization() {
+        assert_eq!(
+            normalize_proxy_url("127.0.0.1:7890"),
+            "http://127.0.0.1:7890"
+        );
+        assert_eq!(
+            normalize_proxy_url("socks5://127.0.0.1:1080"),
+            "socks5://127.0.0.1:1080"
+        );
+        assert_eq!(
+            normalize_proxy_url("http://proxy.example:8080"),
+            "http://proxy.example:8080"
+        );
+    }
+
+    #[test]
+    fn api_number_accepts_common_formats() {
+        let number = |value| parse_api_number("XAUUSD", "price", value);
+        assert_eq!(number("1,234.56"), Some(1234.56));
+        assert_eq!(number("1.23e3"), Some(1230.0));
+        assert_eq!(number(" 2345.6\t"), Some(2345.6));
+        assert_eq!(number("12 345.5"), Some(12345.5));
+    }
+
+    #[test]
+    fn api_number_rejects_malformed_input() {
+        let number = |value| parse_api_number("XAUUSD", "price", value);
+        assert_eq!(number(""), None);
+        assert_eq!(number("abc"), None);
+        assert_eq!(number("1.2.3"), None);
+        assert_eq!(number("NaN"), None);
+        assert_eq!(number("inf"), None);
+        let timestamp = |value| parse_api_timestamp("XAUUSD", value);
+        assert_eq!(timestamp(" 1700000000 "), Some(1700000000));
+        assert_eq!(timestamp("17000x"), None);
+    }
+
+    #[test]
+    fn malformed_kline_is_skipped_not_zero() {
+        let body = r#"{"ret":200,"msg":"ok","data":{"kline_list":[
+            {"code":"XAUUSD","kline_data":[{"timestamp":"1700000000","open_price":"1,990.5","close_price":"2,001.25"}]},
+            {"code":"XAGUSD","kline_data":[{"timestamp":"1700000000","open_price":"23.1","close_price":"n/a"}]}
+        ]}}"#;
+        let payload = serde_json::from_str::<BatchResp>(body).unwrap();
+        let quotes = parse_kline_quotes(payload.data.kline_list, 0);
+        let gold = quotes.get("XAUUSD").unwrap();
+        assert_eq!(gold.price, 2001.25);
+        assert_eq!(gold.open, 1990.5);
+        assert_eq!(gold.timestamp, 1700000000);
+        assert!(!quotes.contains_key("XAGUSD"));
+    }
+
+    #[test]
+    fn smoothed_price_converges_when_raw_stops_changing() {
+        let mut smoothed = HashMap::new();
+        let alpha = Some(0.5);
+        advance_smoothed_price(&mut smoothed, "XAUUSD", 100.0, alpha);
+        advance_smoothed_price(&mut smoothed, "XAUUSD", 110.0, alpha);
+        let read = |smoothed: &HashMap<String, f64>| {
+            smoothed_title_price(smoothed, "XAUUSD", 110.0, alpha)
+        };
+        assert_eq!(read(&smoothed), 105.0);
+        // 渲染只读取缓存，不推进。
+        assert_eq!(read(&smoothed), 105.0);
+        for _ in 0..40 {
+            advance_smoothed_price(&mut smoothed, "XAUUSD", 110.0, alpha);
+        }
+        assert!((read(&smoothed) - 110.0).abs() < 1e-6);
+        // 关闭平滑后直接显示原始价。
+        let raw = smoothed_title_price(&smoothed, "XAUUSD", 111.0, None);
+        assert_eq!(raw, 111.0);
+    }
+
+    #[test]
+    fn endpoint_candidates_try_primary_before_fallbacks() {
+        let endpoints = vec![
+            "https://a.example".to_string(),
+            "https://b.example".to_string(),
+        ];
+        assert_eq!(
+            endpoint_candidates(&endpoints),
+            vec![API_BASE_URL, "https://a.example", "https://b.example"]
+        );
+        assert_eq!(endpoint_candidates(&[]), vec![API_BASE_URL]);
+    }
+
+    #[test]
+    fn stream_url_switches_scheme_to_websocket() {
+        let url = stream_url(API_BASE_URL, ApiType::Commodity, "abc").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "wss://quote.alltick.io/quote-b-ws-api?token=abc"
+        );
+        let url = stream_url("http://mirror.example", ApiType::Stock, "abc").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "ws://mirror.example/quote-stock-b-ws-api?token=abc"
+        );
+    }
+
+    #[test]
+    fn tokens_split_on_mixed_delimiters() {
+        assert_eq!(parse_tokens("a,b;c\nd"), vec!["a", "b", "c", "d"]);
+        assert_eq!(
+            parse_tokens(" a , ,b;;\n\n c ;\r\nd\t"),
+            vec!["a", "b", "c", "d"]
+        );
+        assert!(parse_tokens(" ,;\n ").is_empty());
+    }
+
+    #[test]
+    fn batch_limit_shrinks_and_probes_larger_sizes() {
+        let mut limit = BatchLimit::default();
+        assert_eq!(limit.next_size(10), 10);
+        assert_eq!(limit.shrink(10), 5);
+        assert_eq!(limit.shrink(5), 3);
+        assert_eq!(limit.next_size(10), 3);
+        limit.probe_at = Some(Instant::now());
+        assert_eq!(limit.next_size(10), 6);
+        limit.probe_at = Some(Instant::now());
+        assert_eq!(limit.next_size(10), 10);
+        assert!(limit.size.is_none());
+    }
+
+    #[test]
+    fn batch_too_large_matches_ret_code_only() {
+        assert!(FetchError::with_msg(API_RET_TOO_MANY_CODES, None).is_batch_too_large());
+        let other = FetchError::with_msg(400, Some("too many codes".to_string()));
+        assert!(!other.is_batch_too_large());
+        assert!(!FetchError::new("timeout".to_string()).is_batch_too_large());
+    }
+
+    #[test]
+    fn only_metrics_port_requires_restart() {
+        let old = QuoteSettings::default();
+        let mut live = old.clone();
+        live.locale = "en".to_string();
+        live.transport = Transport::Stream;
+        live.left_click_action = ClickAction::Settings;
+        live.api_type = ApiType::Stock;
+        assert!(!settings_requires_restart(&old, &live));
+        let mut port = old.clone();
+        port.metrics_port = Some(9100);
+        assert!(settings_requires_restart(&old, &port));
+    }
+
+    #[test]
+    fn jump_detection_needs_two_consistent_readings() {
+        let mut suspect = HashMap::new();
+        let mut check =
+            |last, price, limit| accept_price(&mut suspect, "XAUUSD", last, price, limit);
+        assert!(check(Some(100.0), 102.0, Some(5.0)));
+        // 单次跳变暂不采用。
+        assert!(!check(Some(100.0), 120.0, Some(5.0)));
+        // 再次读到相近的价位才接受。
+        assert!(check(Some(100.0), 121.0, Some(5.0)));
+        // 未设置阈值或没有上次价格时直接采用。
+        assert!(check(Some(100.0), 500.0, None));
+        assert!(check(None, 500.0, Some(5.0)));
+        assert!(suspect.is_empty());
+    }
+
+    #[test]
+    fn rounding_modes_at_half_boundary() {
+        let symbol = SymbolItem::default();
+        let price = |value, rounding| {
+            let settings = QuoteSettings {
+                rounding,
+                ..QuoteSettings::default()
+            };
+            format_price(&settings, &symbol, value)
+        };
+        assert_eq!(price(2.675, RoundingMode::HalfUp), "2.68");
+        assert_eq!(price(2.675, RoundingMode::HalfEven), "2.67");
+        assert_eq!(price(1.005, RoundingMode::HalfUp), "1.01");
+        assert_eq!(price(1.005, RoundingMode::HalfEven), "1.00");
+        assert_eq!(price(-2.675, RoundingMode::HalfUp), "-2.68");
+        assert_eq!(price(2.665, RoundingMode::HalfUp), "2.67");
+    }
+
+    #[test]
+    fn round_half_up_keeps_large_values() {
+        assert_eq!(round_half_up(123_456_789.125, 2), 123_456_789.13);
+        assert_eq!(round_half_up(1e15, 2), 1e15);
+        assert_eq!(round_half_up(1e17, 4), 1e17);
+    }
+}